@@ -0,0 +1,88 @@
+use specs::{System, WriteStorage, Join, Read};
+use specs::prelude::Entities;
+use specs::shrev::ReaderId;
+
+use crate::components::{self, AnimationMode};
+use crate::events::{GameEvent, GameEvents};
+
+// img/explosion.png, 8 frames of 64x64 in two rows of 4
+const EXPLOSION_FRAMES: u32 = 8;
+const EXPLOSION_COLUMNS: u32 = 4;
+const EXPLOSION_FRAME_SIZE: u32 = 64;
+const EXPLOSION_FPS: f64 = 20.0;
+
+// Advances every Animation and writes the frame to show into its Renderable
+pub struct Animator;
+
+impl<'a> System<'a> for Animator {
+    type SystemData = (
+        WriteStorage<'a, components::Animation>,
+        WriteStorage<'a, components::Renderable>,
+        Entities<'a>,
+        Read<'a, crate::DeltaTime>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut animations, mut renderables, entities, deltatime) = data;
+
+        for (animation, rend, entity) in (&mut animations, &mut renderables, &entities).join() {
+            animation.elapsed += deltatime.0;
+            let total_frames = rend.total_frames.max(1);
+            let frame = (animation.elapsed * animation.fps) as u32;
+
+            rend.frame = match animation.mode {
+                AnimationMode::Loop => frame % total_frames,
+                AnimationMode::Once => frame.min(total_frames - 1),
+                AnimationMode::OnceThenDespawn => {
+                    if frame >= total_frames {
+                        entities.delete(entity).ok();
+                    }
+                    frame.min(total_frames - 1)
+                }
+            };
+        }
+    }
+}
+
+// Leaves a short explosion wherever an asteroid was destroyed, sized to the asteroid
+pub struct ExplosionSpawner {
+    reader: ReaderId<GameEvent>
+}
+
+impl ExplosionSpawner {
+    pub fn new(reader: ReaderId<GameEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'a> System<'a> for ExplosionSpawner {
+    type SystemData = (
+        WriteStorage<'a, components::Position>,
+        WriteStorage<'a, components::Renderable>,
+        WriteStorage<'a, components::Animation>,
+        Entities<'a>,
+        Read<'a, GameEvents>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut positions, mut renderables, mut animations, entities, events) = data;
+
+        for event in events.read(&mut self.reader) {
+            if let GameEvent::AsteroidDestroyed { x, y, size } = *event {
+                let explosion = entities.create();
+                positions.insert(explosion, components::Position{x, y, rot: 0.0}).ok();
+                renderables.insert(explosion, components::Renderable{
+                    texture: "explosion".to_string(),
+                    i_w: EXPLOSION_FRAME_SIZE,
+                    i_h: EXPLOSION_FRAME_SIZE,
+                    o_w: size,
+                    o_h: size,
+                    frame: 0,
+                    total_frames: EXPLOSION_FRAMES,
+                    rot: 0.0
+                }).ok();
+                animations.insert(explosion, components::Animation::new(EXPLOSION_FPS, AnimationMode::OnceThenDespawn, EXPLOSION_COLUMNS)).ok();
+            }
+        }
+    }
+}
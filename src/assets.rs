@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::ttf::FontStyle;
+use sdl2::video::WindowContext;
+
+use crate::texture_manager::FontDetails;
+
+// Why an image, sound or font could not be used
+#[derive(Debug)]
+pub enum AssetError {
+    NotFound { path: String },
+    Decode { path: String, reason: String },
+    Unsupported { path: String, reason: String },
+    UnknownId { id: String }
+}
+
+impl AssetError {
+    // Missing files get their own error, anything else the loader complained about is a decode error
+    pub fn from_load(path: &str, reason: impl ToString) -> Self {
+        if Path::new(path).exists() {
+            AssetError::Decode { path: path.to_string(), reason: reason.to_string() }
+        } else {
+            AssetError::NotFound { path: path.to_string() }
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            AssetError::NotFound { path }
+            | AssetError::Decode { path, .. }
+            | AssetError::Unsupported { path, .. } => path,
+            AssetError::UnknownId { id } => id
+        }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound { path } => write!(f, "{} is missing", path),
+            AssetError::Decode { path, reason } => write!(f, "{} could not be read: {}", path, reason),
+            AssetError::Unsupported { path, reason } => write!(f, "{} is not supported: {}", path, reason),
+            AssetError::UnknownId { id } => write!(f, "No asset called {} in the manifest", id)
+        }
+    }
+}
+
+// Lets main keep using `?` with its String errors
+impl From<AssetError> for String {
+    fn from(error: AssetError) -> Self {
+        error.to_string()
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Texture,
+    Sound,
+    Font
+}
+
+// One [[asset]] table in the manifest
+#[derive(Deserialize)]
+pub struct AssetEntry {
+    pub id: String,
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: AssetKind,
+    pub group: String,
+    #[serde(default)]
+    pub size: Option<u16>, //Point size, fonts only
+    #[serde(default)]
+    pub bold: bool //Fonts only
+}
+
+// Every asset the game knows about, read from a TOML file like assets.toml
+#[derive(Deserialize)]
+pub struct AssetManifest {
+    #[serde(rename = "asset", default)]
+    assets: Vec<AssetEntry>
+}
+
+impl AssetManifest {
+    pub fn load(path: &str) -> Result<Self, AssetError> {
+        let text = fs::read_to_string(path).map_err(|e| AssetError::from_load(path, e))?;
+        toml::from_str(&text).map_err(|e| AssetError::Decode { path: path.to_string(), reason: e.to_string() })
+    }
+
+    pub fn get(&self, id: &str) -> Result<&AssetEntry, AssetError> {
+        self.assets.iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| AssetError::UnknownId { id: id.to_string() })
+    }
+
+    // Every entry loaded from the given file, several ids may share one
+    pub fn with_path<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a AssetEntry> + 'a {
+        self.assets.iter().filter(move |entry| entry.path == path)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &AssetEntry> {
+        self.assets.iter()
+    }
+
+    pub fn path(&self, id: &str) -> Option<&str> {
+        self.get(id).ok().map(|entry| entry.path.as_str())
+    }
+
+    // Everything that should be loaded together, e.g. the "core" group before the game starts
+    pub fn group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a AssetEntry> + 'a {
+        self.assets.iter().filter(move |entry| entry.group == group)
+    }
+
+    pub fn font(&self, id: &str) -> Result<FontDetails, AssetError> {
+        let entry = self.get(id)?;
+        if entry.kind != AssetKind::Font {
+            return Err(AssetError::Unsupported { path: entry.path.to_string(), reason: format!("{} is not a font", id) });
+        }
+        Ok(FontDetails {
+            path: entry.path.to_string(),
+            size: entry.size.unwrap_or(DEFAULT_FONT_SIZE),
+            style: if entry.bold { FontStyle::BOLD } else { FontStyle::NORMAL }
+        })
+    }
+}
+
+const DEFAULT_FONT_SIZE: u16 = 32;
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Notices when watched files change on disk so they can be reloaded while the game runs.
+// It polls modification times, which is cheap for a handful of files and needs no extra threads
+pub struct AssetWatcher {
+    files: HashMap<String, Option<SystemTime>>,
+    last_poll: Instant
+}
+
+impl AssetWatcher {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+            last_poll: Instant::now()
+        }
+    }
+
+    pub fn watch(&mut self, path: &str) {
+        self.files.entry(path.to_string()).or_insert_with(|| modified(path));
+    }
+
+    // Every watched file whose modification time moved since the last poll
+    pub fn changed(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < WATCH_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, last_modified) in self.files.iter_mut() {
+            let now_modified = modified(path);
+            if now_modified != *last_modified {
+                *last_modified = now_modified;
+                //A file that vanished is left alone until it comes back
+                if now_modified.is_some() {
+                    changed.push(path.to_string());
+                }
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Collects every asset that failed during startup so they can be listed together
+#[derive(Default)]
+pub struct AssetReport {
+    failures: Vec<AssetError>
+}
+
+impl AssetReport {
+    pub fn check<T>(&mut self, result: Result<T, AssetError>) -> Option<T> {
+        match result {
+            Ok(asset) => Some(asset),
+            Err(e) => {
+                self.failures.push(e);
+                None
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        if self.failures.is_empty() {
+            println!("All assets loaded");
+            return;
+        }
+        println!("{} asset(s) failed to load, using placeholders:", self.failures.len());
+        for failure in self.failures.iter() {
+            println!("  {}", failure);
+        }
+    }
+}
+
+const PLACEHOLDER_SIZE: u32 = 64;
+const PLACEHOLDER_SQUARE: u32 = 16;
+
+// Magenta and black checkerboard drawn in place of any texture that failed to load
+pub fn placeholder_texture<'l>(canvas: &mut WindowCanvas, texture_creator: &'l TextureCreator<WindowContext>) -> Result<Texture<'l>, String> {
+    let mut texture = texture_creator
+        .create_texture_target(None, PLACEHOLDER_SIZE, PLACEHOLDER_SIZE)
+        .map_err(|e| e.to_string())?;
+    let mut result = Ok(());
+    canvas.with_texture_canvas(&mut texture, |texture_canvas| {
+        texture_canvas.set_draw_color(Color::RGB(0, 0, 0));
+        texture_canvas.clear();
+        texture_canvas.set_draw_color(Color::RGB(255, 0, 255));
+        for column in 0..PLACEHOLDER_SIZE / PLACEHOLDER_SQUARE {
+            for row in 0..PLACEHOLDER_SIZE / PLACEHOLDER_SQUARE {
+                if (column + row) % 2 == 0 {
+                    result = texture_canvas.fill_rect(Rect::new(
+                        (column * PLACEHOLDER_SQUARE) as i32,
+                        (row * PLACEHOLDER_SQUARE) as i32,
+                        PLACEHOLDER_SQUARE,
+                        PLACEHOLDER_SQUARE
+                    ));
+                }
+            }
+        }
+    }).map_err(|e| e.to_string())?;
+    result.map(|_| texture)
+}
@@ -1,90 +1,153 @@
-use specs::{System, WriteStorage, Join, Read};
+use specs::{System, WriteStorage, ReadStorage, Read, Write};
 use specs::prelude::Entities;
 
-pub struct AsteroidMover;
+use crate::{components, collision, physics};
+use crate::events::{GameEvent, GameEvents};
 
-use crate::{components, NO_OF_SECTIONS, SECTION_HEIGHT, SECTION_WIDTH};
+pub struct AsteroidCollider;
 
-impl<'a> System<'a> for AsteroidMover{
+impl<'a> System<'a> for AsteroidCollider{
     type SystemData = (
-        WriteStorage<'a, components::Position>,
-        WriteStorage<'a, components::Renderable>,
-        WriteStorage<'a, components::Asteroid>,
-        Read<'a,crate::DeltaTime>
+        WriteStorage<'a, components::Player>,
+        ReadStorage<'a, components::Asteroid>,
+        Read<'a, collision::Contacts>,
+        Write<'a, GameEvents>,
+        Entities<'a>
     );
 
-    fn run(&mut self, mut data: Self::SystemData) {
-        let deltatime = data.3.0;
-        for(pos,rend,asteroid) in (&mut data.0, &mut data.1, &data.2).join(){
-            let radians = pos.rot.to_radians();
-
-            pos.x += asteroid.speed * radians.sin() * deltatime;
-            pos.y -= asteroid.speed * radians.cos() * deltatime;
-
-            pos.section = (pos.x as u32/ SECTION_WIDTH) * NO_OF_SECTIONS +  (pos.y as u32 / SECTION_HEIGHT);
-
-            let half_width = (rend.o_w / 2) as u32;
-            let half_height = (rend.o_h / 2) as u32;
-
-            if pos.x > (crate::SCREEN_WIDTH - half_width).into()
-                || pos.x < half_width.into(){
-                    pos.rot = 360.0 - pos.rot;
-            } else if pos.y > (crate::SCREEN_HEIGHT - half_height).into()
-                || pos.y < half_height.into(){
-                    if pos.rot > 180.0 {
-                        pos.rot = 540.0 - pos.rot;
-                    } else {
-                        pos.rot = 180.0 - pos.rot;
-                    }
-            }
-
-            rend.rot += asteroid.rot_speed * deltatime;
-            if rend.rot > 360.0 {
-                rend.rot -= 360.0;
+    // fn run(&mut self, mut data: Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut players, asteroids, contacts, mut events, entities) = data;
+        for contact in contacts.0.iter() {
+            if asteroids.get(contact.b).is_none() {
+                continue;
             }
-            if rend.rot < 0.0 {
-                rend.rot += 360.0;
+            if let Some(player) = players.get_mut(contact.a) {
+                if player.invulnerable || player.died {
+                    continue;
+                }
+                //Marked either way so a second asteroid touching them this tick doesn't kill them again
+                player.died = true;
+                events.single_write(GameEvent::PlayerDied{lives_left: player.lives - 1});
+                if player.lives <= 1 {
+                    let _ = entities.delete(contact.a);
+                }
             }
-
         }
     }
 }
 
-pub struct AsteroidCollider;
+// Elastic collisions between asteroids, only fed contacts when CollisionRules::asteroid_collisions is on.
+// Mass comes from the asteroid's area so big rocks shove small ones around.
+pub struct AsteroidBouncer;
 
-impl<'a> System<'a> for AsteroidCollider{
+impl<'a> System<'a> for AsteroidBouncer{
     type SystemData = (
         WriteStorage<'a, components::Position>,
-        WriteStorage<'a, components::Renderable>,
-        WriteStorage<'a, components::Player>,
-        WriteStorage<'a, components::Asteroid>,
-        Entities<'a>
+        WriteStorage<'a, components::Velocity>,
+        ReadStorage<'a, components::Renderable>,
+        ReadStorage<'a, components::Asteroid>,
+        ReadStorage<'a, components::Collider>,
+        ReadStorage<'a, components::ScreenEdge>,
+        Read<'a, collision::Contacts>,
+        Read<'a, physics::Playfield>
     );
 
-    // fn run(&mut self, mut data: Self::SystemData) {
     fn run(&mut self, data: Self::SystemData) {
-        let (positions, rends, mut players, asteroids, entities) = data;
-        for(players_pos, player_rend, player, entity) in (&positions,&rends, &mut players, &entities).join(){
-            if player.invulnerable || player.died {
+        let (mut positions, mut velocities, rends, asteroids, colliders, edges, contacts, playfield) = data;
+        for contact in contacts.0.iter() {
+            let (a, b) = (contact.a, contact.b);
+            if asteroids.get(a).is_none() || asteroids.get(b).is_none() {
                 continue;
             }
-            for(asteroid_pos, asteroid_rend, _) in (&positions, &rends, &asteroids).join(){
-                if asteroid_pos.section != players_pos.section {
-                    continue;
-                }
-                let diff_x: f64 = (players_pos.x - asteroid_pos.x).abs();
-                let diff_y: f64 = (players_pos.y - asteroid_pos.y).abs();
-                let hype: f64 = ((diff_x*diff_x) + (diff_y*diff_y));
+            let (pos_a, pos_b) = match (positions.get(a), positions.get(b)) {
+                (Some(pos_a), Some(pos_b)) => ((pos_a.x, pos_a.y), (pos_b.x, pos_b.y)),
+                _ => continue
+            };
+            let (mut vel_a, mut vel_b) = match (velocities.get(a), velocities.get(b)) {
+                (Some(vel_a), Some(vel_b)) => ((vel_a.x, vel_a.y), (vel_b.x, vel_b.y)),
+                _ => continue
+            };
+            let (mass_a, mass_b) = match (rends.get(a), rends.get(b)) {
+                (Some(rend_a), Some(rend_b)) => ((rend_a.o_w * rend_a.o_h) as f64, (rend_b.o_w * rend_b.o_h) as f64),
+                _ => continue
+            };
+            let reach = match (colliders.get(a), colliders.get(b)) {
+                (Some(collider_a), Some(collider_b)) => collider_a.radius + collider_b.radius,
+                _ => continue
+            };
 
-                if hype < ((player_rend.o_w + asteroid_rend.o_w) as f64 / 2.0)*((player_rend.o_w + asteroid_rend.o_w) as f64 / 2.0) {
-                    println!("Collision Detected!");
-                    if player.lives > 1 {
-                        player.died = true;
-                    } else {
-                        let _ = entities.delete(entity);
-                    }
-                }
+            //Collision normal pointing from a to b
+            let (offset_x, offset_y) = collision::offset_between(pos_a, pos_b, collision::both_wrap(&edges, a, b), &playfield);
+            let dist = (offset_x * offset_x + offset_y * offset_y).sqrt();
+            if dist == 0.0 {
+                continue;
+            }
+            let normal_x = offset_x / dist;
+            let normal_y = offset_y / dist;
+
+            //Only exchange momentum if they are moving towards each other
+            let closing = (vel_a.0 - vel_b.0) * normal_x + (vel_a.1 - vel_b.1) * normal_y;
+            if closing > 0.0 {
+                let impulse = 2.0 * closing / (mass_a + mass_b);
+                vel_a.0 -= impulse * mass_b * normal_x;
+                vel_a.1 -= impulse * mass_b * normal_y;
+                vel_b.0 += impulse * mass_a * normal_x;
+                vel_b.1 += impulse * mass_a * normal_y;
+            }
+
+            //Push them apart so they don't stay stuck together, the lighter one moves further
+            let overlap = reach - dist;
+            let push_a = overlap * mass_b / (mass_a + mass_b);
+            let push_b = overlap * mass_a / (mass_a + mass_b);
+
+            if let Some(pos) = positions.get_mut(a) {
+                pos.x -= normal_x * push_a;
+                pos.y -= normal_y * push_a;
+            }
+            if let Some(pos) = positions.get_mut(b) {
+                pos.x += normal_x * push_b;
+                pos.y += normal_y * push_b;
+            }
+            if let Some(vel) = velocities.get_mut(a) {
+                vel.x = vel_a.0;
+                vel.y = vel_a.1;
+            }
+            if let Some(vel) = velocities.get_mut(b) {
+                vel.x = vel_b.0;
+                vel.y = vel_b.1;
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+    use vector2d::Vector2D;
+
+    #[test]
+    fn last_life_lost_once_when_two_asteroids_hit_together() {
+        let mut world = World::new();
+        world.register::<components::Player>();
+        world.register::<components::Asteroid>();
+        world.insert(GameEvents::new());
+        let mut reader = world.write_resource::<GameEvents>().register_reader();
+
+        let player = world.create_entity()
+            .with(components::Player { impulse: Vector2D::new(0.0, 0.0), lives: 1, died: false, thrusting: false, invulnerable: false })
+            .build();
+        let first = world.create_entity().with(components::Asteroid).build();
+        let second = world.create_entity().with(components::Asteroid).build();
+        world.insert(collision::Contacts(vec![
+            collision::Contact { a: player, b: first },
+            collision::Contact { a: player, b: second }
+        ]));
+
+        AsteroidCollider.run_now(&world);
+        let events = world.read_resource::<GameEvents>();
+        let published: Vec<GameEvent> = events.read(&mut reader).copied().collect();
+        assert_eq!(published, vec![GameEvent::PlayerDied { lives_left: 0 }]);
+    }
+}
@@ -0,0 +1,10 @@
+// Runs the simulation without a window or audio, for machines that don't have SDL or a sound device.
+// Takes the same flags as the game, e.g. `headless --headless 1000 --seed 1` or `headless --replay run.rep`
+use asteroids_assignment::cli;
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let (settings, loaded_replay) = cli::settings_from_args(&args)?;
+    println!("Seed: {}", settings.seed);
+    cli::run_headless(&args, &settings, loaded_replay)
+}
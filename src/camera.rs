@@ -0,0 +1,52 @@
+use crate::components::EdgePolicy;
+use crate::physics::Playfield;
+
+// The part of the playfield shown on screen, kept as a World resource by the renderer.
+// It only moves when the playfield is bigger than the view, so a screen sized arena is drawn exactly as before
+pub struct Camera {
+    pub x: f64, //World position at the centre of the view
+    pub y: f64,
+    pub view_width: u32,
+    pub view_height: u32
+}
+
+impl Camera {
+    pub fn new(view_width: u32, view_height: u32) -> Self {
+        Self {
+            x: view_width as f64 / 2.0,
+            y: view_height as f64 / 2.0,
+            view_width,
+            view_height
+        }
+    }
+
+    // Centres on the target. If the player wraps the view runs across the seam with them,
+    // otherwise it stops at the playfield edges
+    pub fn follow(&mut self, target: (f64, f64), playfield: &Playfield) {
+        let wraps = playfield.edges.player == EdgePolicy::Wrap;
+        self.x = follow_axis(target.0, playfield.width, self.view_width, wraps);
+        self.y = follow_axis(target.1, playfield.height, self.view_height, wraps);
+    }
+
+    pub fn world_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            x - self.x + self.view_width as f64 / 2.0,
+            y - self.y + self.view_height as f64 / 2.0
+        )
+    }
+
+    pub fn scrolls(&self, playfield: &Playfield) -> bool {
+        playfield.width > self.view_width || playfield.height > self.view_height
+    }
+}
+
+fn follow_axis(target: f64, field: u32, view: u32, wraps: bool) -> f64 {
+    let (field, half_view) = (field as f64, view as f64 / 2.0);
+    if field <= view as f64 {
+        field / 2.0
+    } else if wraps {
+        target
+    } else {
+        target.clamp(half_view, field - half_view)
+    }
+}
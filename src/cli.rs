@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use crate::{components, physics, replay, simulation};
+
+// Simulation ticks per second unless `--tick-rate` says otherwise
+const DEFAULT_TICK_RATE: u32 = 60;
+
+// Looks up the value following a command line flag, e.g. `--tick-rate 120`
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == flag).and_then(|index| args.get(index + 1))
+}
+
+// Reads an edge policy flag such as `--asteroid-edge bounce`
+fn edge_arg(args: &[String], flag: &str, default: components::EdgePolicy) -> components::EdgePolicy {
+    arg_value(args, flag)
+        .and_then(|name| components::EdgePolicy::from_name(name))
+        .unwrap_or(default)
+}
+
+// Parses a `--window-size 1280x720` style value
+pub fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    match (width.parse::<u32>(), height.parse::<u32>()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+        _ => None
+    }
+}
+
+// Builds the simulation settings from the command line. A `--replay` brings its own settings, so it is loaded here too
+pub fn settings_from_args(args: &[String]) -> Result<(simulation::SimulationSettings, Option<replay::Replay>), String> {
+    let tick_rate = arg_value(args, "--tick-rate")
+        .and_then(|rate| rate.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_TICK_RATE);
+    let seed = arg_value(args, "--seed")
+        .and_then(|seed| seed.parse::<u64>().ok())
+        .unwrap_or_else(rand::random::<u64>);
+
+    //The arena defaults to the screen, e.g. `--playfield 3840x2160 --grid-cell 256` for a bigger one
+    let defaults = physics::Playfield::default();
+    let (field_width, field_height) = match arg_value(args, "--playfield") {
+        Some(size) => match parse_size(size) {
+            Some((width, height)) if width >= physics::MIN_PLAYFIELD_SIZE && height >= physics::MIN_PLAYFIELD_SIZE => (width, height),
+            _ => return Err(format!("Bad playfield size {}, expected WIDTHxHEIGHT of at least {}x{}",
+                size, physics::MIN_PLAYFIELD_SIZE, physics::MIN_PLAYFIELD_SIZE))
+        },
+        None => (defaults.width, defaults.height)
+    };
+    let settings = simulation::SimulationSettings{
+        seed,
+        tick_rate,
+        asteroid_collisions: args.iter().any(|arg| arg == "--asteroid-collisions"),
+        playfield: physics::Playfield{
+            width: field_width,
+            height: field_height,
+            grid_cell_size: arg_value(args, "--grid-cell")
+                .and_then(|size| size.parse::<u32>().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(defaults.grid_cell_size),
+            edges: physics::EdgePolicies{
+                player: edge_arg(args, "--player-edge", defaults.edges.player),
+                asteroid: edge_arg(args, "--asteroid-edge", defaults.edges.asteroid),
+                missile: edge_arg(args, "--missile-edge", defaults.edges.missile)
+            }
+        }
+    };
+
+    let loaded_replay = match arg_value(args, "--replay") {
+        Some(path) => Some(replay::Replay::load(Path::new(path))?),
+        None => None
+    };
+    let settings = match &loaded_replay {
+        Some(replay) => replay.settings,
+        None => settings
+    };
+    Ok((settings, loaded_replay))
+}
+
+// Checks a replay, runs a benchmark or plays bot games, whichever the arguments ask for
+pub fn run_headless(args: &[String], settings: &simulation::SimulationSettings, loaded_replay: Option<replay::Replay>) -> Result<(), String> {
+    //Check a replay's score, e.g. `--headless --replay run.rep`
+    if let Some(replay) = loaded_replay {
+        let recorded_score = replay.final_score;
+        let score = simulation::run_replay(replay);
+        println!("Replay finished with score {} (recorded {})", score, recorded_score);
+        if score != recorded_score {
+            return Err("Replay score does not match the recorded score".to_string());
+        }
+        return Ok(());
+    }
+
+    //Time the simulation with lots of asteroids, e.g. `--headless --bench 10000`
+    if let Some(asteroids) = arg_value(args, "--bench").and_then(|count| count.parse::<u32>().ok()) {
+        simulation::run_benchmark(asteroids, settings);
+        return Ok(());
+    }
+
+    //Run bot games, e.g. `--headless 1000`
+    let games = arg_value(args, "--headless")
+        .and_then(|games| games.parse::<u32>().ok())
+        .unwrap_or(1);
+    simulation::run_headless(games, settings);
+    Ok(())
+}
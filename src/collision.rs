@@ -0,0 +1,330 @@
+use specs::{System, ReadStorage, Read, Write, WriteExpect, Join, Entity};
+use specs::prelude::Entities;
+
+use crate::components::{self, CollisionLayer, EdgePolicy};
+use crate::physics::Playfield;
+
+// Uniform grid over the playfield, rebuilt every tick.
+// Each entity goes into every cell its radius touches, wrapping around the playfield edges,
+// so anything that could be touching it is in one of the cells it looks up.
+pub struct SpatialGrid {
+    cell_width: f64,
+    cell_height: f64,
+    columns: i32,
+    rows: i32,
+    cells: Vec<Vec<Entity>>
+}
+
+impl SpatialGrid {
+    // Cells are stretched slightly so a whole number of them fits the playfield, keeping wrapping exact
+    pub fn new(width: u32, height: u32, cell_size: u32) -> Self {
+        let columns = (width / cell_size.max(1)).max(1) as i32;
+        let rows = (height / cell_size.max(1)).max(1) as i32;
+        Self {
+            cell_width: width as f64 / columns as f64,
+            cell_height: height as f64 / rows as f64,
+            columns,
+            rows,
+            cells: vec![Vec::new(); (columns * rows) as usize]
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, x: f64, y: f64, radius: f64) {
+        for index in self.cells_covering(x, y, radius) {
+            self.cells[index].push(entity);
+        }
+    }
+
+    // Every entity sharing a cell with the given circle, each listed once
+    pub fn query(&self, x: f64, y: f64, radius: f64) -> Vec<Entity> {
+        let mut found = Vec::new();
+        for index in self.cells_covering(x, y, radius) {
+            found.extend_from_slice(&self.cells[index]);
+        }
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    fn cells_covering(&self, x: f64, y: f64, radius: f64) -> Vec<usize> {
+        let (first_column, last_column) = span(x, radius, self.cell_width, self.columns);
+        let (first_row, last_row) = span(y, radius, self.cell_height, self.rows);
+
+        let mut indices = Vec::new();
+        for column in first_column..=last_column {
+            for row in first_row..=last_row {
+                let column = column.rem_euclid(self.columns);
+                let row = row.rem_euclid(self.rows);
+                indices.push((column * self.rows + row) as usize);
+            }
+        }
+        indices
+    }
+}
+
+// Range of cells a circle covers along one axis, never more than the whole axis
+fn span(centre: f64, radius: f64, cell_size: f64, count: i32) -> (i32, i32) {
+    let first = ((centre - radius) / cell_size).floor() as i32;
+    let last = ((centre + radius) / cell_size).floor() as i32;
+    (first, last.min(first + count - 1))
+}
+
+// Shortest distance between two points along an axis that wraps at size
+pub fn wrapped_delta(a: f64, b: f64, size: f64) -> f64 {
+    let delta = (a - b).abs() % size;
+    if delta > size / 2.0 {
+        size - delta
+    } else {
+        delta
+    }
+}
+
+// Signed shortest offset from one point to another along an axis that wraps at size
+pub fn wrapped_offset(from: f64, to: f64, size: f64) -> f64 {
+    let offset = (to - from) % size;
+    if offset > size / 2.0 {
+        offset - size
+    } else if offset < -size / 2.0 {
+        offset + size
+    } else {
+        offset
+    }
+}
+
+// Squared distance between two positions, taking the shorter way around the playfield
+pub fn wrapped_distance_squared(a: &components::Position, b: &components::Position, playfield: &Playfield) -> f64 {
+    let diff_x = wrapped_delta(a.x, b.x, playfield.width as f64);
+    let diff_y = wrapped_delta(a.y, b.y, playfield.height as f64);
+    diff_x * diff_x + diff_y * diff_y
+}
+
+// True if both entities wrap around the playfield, only then can they touch across the seam
+pub fn both_wrap(edges: &ReadStorage<components::ScreenEdge>, a: Entity, b: Entity) -> bool {
+    let wraps = |entity| matches!(edges.get(entity), Some(components::ScreenEdge(EdgePolicy::Wrap)));
+    wraps(a) && wraps(b)
+}
+
+// Signed offset from one point to another, across the seam only when both ends wrap
+pub fn offset_between(from: (f64, f64), to: (f64, f64), wraps: bool, playfield: &Playfield) -> (f64, f64) {
+    if wraps {
+        (
+            wrapped_offset(from.0, to.0, playfield.width as f64),
+            wrapped_offset(from.1, to.1, playfield.height as f64)
+        )
+    } else {
+        (to.0 - from.0, to.1 - from.1)
+    }
+}
+
+// Two colliders that overlapped this tick, `a` is always on the lower CollisionLayer
+pub struct Contact {
+    pub a: Entity,
+    pub b: Entity
+}
+
+// Every contact found by the Broadphase this tick, read by the gameplay systems
+#[derive(Default)]
+pub struct Contacts(pub Vec<Contact>);
+
+// Which layers produce contacts with each other
+#[derive(Default)]
+pub struct CollisionRules {
+    pub asteroid_collisions: bool
+}
+
+impl CollisionRules {
+    pub fn layers_interact(&self, a: CollisionLayer, b: CollisionLayer) -> bool {
+        match (a, b) {
+            (CollisionLayer::Player, CollisionLayer::Asteroid)
+            | (CollisionLayer::Missile, CollisionLayer::Asteroid) => true,
+            (CollisionLayer::Asteroid, CollisionLayer::Asteroid) => self.asteroid_collisions,
+            _ => false
+        }
+    }
+
+    // True if anything on this layer or a higher one can touch it.
+    // Pairs are looked up from the lower layer, so asteroids only query the grid when they collide with each other.
+    fn looks_for_contacts(&self, layer: CollisionLayer) -> bool {
+        [CollisionLayer::Player, CollisionLayer::Missile, CollisionLayer::Asteroid].iter()
+            .any(|other| *other >= layer && self.layers_interact(layer, *other))
+    }
+}
+
+// Rebuilds the SpatialGrid and turns it into this tick's list of Contacts
+pub struct Broadphase;
+
+impl<'a> System<'a> for Broadphase {
+    type SystemData = (
+        ReadStorage<'a, components::Position>,
+        ReadStorage<'a, components::Collider>,
+        WriteExpect<'a, SpatialGrid>,
+        Write<'a, Contacts>,
+        Read<'a, CollisionRules>,
+        ReadStorage<'a, components::ScreenEdge>,
+        ReadStorage<'a, components::Despawned>,
+        Entities<'a>,
+        Read<'a, Playfield>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (positions, colliders, mut grid, mut contacts, rules, edges, despawned, entities, playfield) = data;
+
+        //Anything deleted earlier this tick is left out, so it can't still hit something on its way out
+        grid.clear();
+        for (pos, collider, entity, _) in (&positions, &colliders, &entities, !&despawned).join() {
+            grid.insert(entity, pos.x, pos.y, collider.radius);
+        }
+
+        contacts.0.clear();
+        for (pos, collider, entity, _) in (&positions, &colliders, &entities, !&despawned).join() {
+            if !rules.looks_for_contacts(collider.layer) {
+                continue;
+            }
+            for other in grid.query(pos.x, pos.y, collider.radius) {
+                let (other_pos, other_collider) = match (positions.get(other), colliders.get(other)) {
+                    (Some(pos), Some(collider)) => (pos, collider),
+                    _ => continue
+                };
+                if other_collider.layer < collider.layer
+                    || (other_collider.layer == collider.layer && other <= entity)
+                    || !rules.layers_interact(collider.layer, other_collider.layer) {
+                    continue;
+                }
+
+                //Things that bounce or despawn at the edge never reach the other side, so they are measured straight across
+                let reach = collider.radius + other_collider.radius;
+                let distance_squared = if both_wrap(&edges, entity, other) {
+                    wrapped_distance_squared(pos, other_pos, &playfield)
+                } else {
+                    (pos.x - other_pos.x).powi(2) + (pos.y - other_pos.y).powi(2)
+                };
+                if distance_squared < reach * reach {
+                    contacts.0.push(Contact{a: entity, b: other});
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn entities(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.create_entity().build()).collect()
+    }
+
+    #[test]
+    fn span_handles_negative_centres() {
+        assert_eq!(span(-10.0, 5.0, 100.0, 10), (-1, -1));
+        assert_eq!(span(-10.0, 20.0, 100.0, 10), (-1, 0));
+    }
+
+    #[test]
+    fn span_covers_radius_larger_than_a_cell() {
+        assert_eq!(span(150.0, 250.0, 100.0, 10), (-1, 4));
+    }
+
+    #[test]
+    fn span_never_covers_more_than_the_whole_axis() {
+        let (first, last) = span(50.0, 5000.0, 100.0, 10);
+        assert_eq!(last - first + 1, 10);
+    }
+
+    #[test]
+    fn cells_covering_wraps_negative_centres_to_the_far_side() {
+        let grid = SpatialGrid::new(1000, 1000, 100);
+        //Column -1 is the last column, row 0 stays put
+        assert_eq!(grid.cells_covering(-10.0, 50.0, 5.0), vec![(9 * 10) as usize]);
+    }
+
+    #[test]
+    fn cells_covering_lists_each_cell_once_for_huge_radii() {
+        let grid = SpatialGrid::new(1000, 500, 100);
+        let mut cells = grid.cells_covering(500.0, 250.0, 10_000.0);
+        let covered = cells.len();
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), covered);
+        assert_eq!(covered, 10 * 5);
+    }
+
+    #[test]
+    fn circles_crossing_the_seam_are_found_from_the_other_side() {
+        let found = entities(2);
+        let mut grid = SpatialGrid::new(1000, 1000, 100);
+        grid.insert(found[0], 995.0, 500.0, 10.0);
+        grid.insert(found[1], 500.0, 995.0, 10.0);
+
+        assert_eq!(grid.query(3.0, 500.0, 5.0), vec![found[0]]);
+        assert_eq!(grid.query(500.0, 3.0, 5.0), vec![found[1]]);
+        assert!(grid.query(500.0, 500.0, 5.0).is_empty());
+    }
+
+    #[test]
+    fn query_lists_an_entity_once_even_if_it_fills_the_grid() {
+        let found = entities(1);
+        let mut grid = SpatialGrid::new(1000, 1000, 100);
+        grid.insert(found[0], 500.0, 500.0, 10_000.0);
+        assert_eq!(grid.query(500.0, 500.0, 10_000.0), found);
+    }
+
+    #[test]
+    fn wrapped_offset_takes_the_short_way_across_the_seam() {
+        assert_eq!(wrapped_offset(990.0, 10.0, 1000.0), 20.0);
+        assert_eq!(wrapped_offset(10.0, 990.0, 1000.0), -20.0);
+        assert_eq!(wrapped_offset(10.0, -10.0, 1000.0), -20.0);
+        assert_eq!(wrapped_offset(-10.0, 990.0, 1000.0), 0.0);
+        assert_eq!(wrapped_offset(100.0, 300.0, 1000.0), 200.0);
+    }
+
+    #[test]
+    fn wrapped_distance_measures_across_the_seam() {
+        let playfield = Playfield { width: 1000, height: 800, ..Playfield::default() };
+        let a = components::Position { x: 995.0, y: 5.0, rot: 0.0 };
+        let b = components::Position { x: 5.0, y: 795.0, rot: 0.0 };
+        assert_eq!(wrapped_distance_squared(&a, &b, &playfield), 10.0 * 10.0 + 10.0 * 10.0);
+    }
+
+    fn contacts_across_the_seam(missile_edge: EdgePolicy, asteroid_edge: EdgePolicy) -> usize {
+        let playfield = Playfield::default();
+        let mut world = World::new();
+        world.register::<components::Position>();
+        world.register::<components::Collider>();
+        world.register::<components::ScreenEdge>();
+        world.register::<components::Despawned>();
+        world.insert(SpatialGrid::new(playfield.width, playfield.height, playfield.grid_cell_size));
+        world.insert(Contacts::default());
+        world.insert(CollisionRules::default());
+        world.insert(playfield);
+
+        let width = playfield.width as f64;
+        for (x, layer, edge) in [(2.0, CollisionLayer::Missile, missile_edge), (width - 40.0, CollisionLayer::Asteroid, asteroid_edge)] {
+            world.create_entity()
+                .with(components::Position { x, y: 500.0, rot: 0.0 })
+                .with(components::Collider { radius: 50.0, layer })
+                .with(components::ScreenEdge(edge))
+                .build();
+        }
+
+        let mut broadphase = Broadphase;
+        broadphase.run_now(&world);
+        let found = world.read_resource::<Contacts>().0.len();
+        found
+    }
+
+    #[test]
+    fn only_wrapping_pairs_touch_across_the_seam() {
+        assert_eq!(contacts_across_the_seam(EdgePolicy::Wrap, EdgePolicy::Wrap), 1);
+        assert_eq!(contacts_across_the_seam(EdgePolicy::Despawn, EdgePolicy::Bounce), 0);
+        assert_eq!(contacts_across_the_seam(EdgePolicy::Wrap, EdgePolicy::Bounce), 0);
+    }
+}
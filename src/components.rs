@@ -6,14 +6,67 @@ use vector2d::Vector2D;
 pub struct Position {
     pub x: f64,
     pub y: f64,
-    pub rot: f64,
-    pub section: u32
+    pub rot: f64
+}
+
+// Where the entity was at the start of the last simulation tick, used to interpolate rendering
+#[derive(Component)]
+pub struct PreviousPosition {
+    pub x: f64,
+    pub y: f64,
+    pub rot: f64
+}
+
+// How fast an entity travels, in pixels per second
+#[derive(Component)]
+pub struct Velocity {
+    pub x: f64,
+    pub y: f64
+}
+
+// How fast an entity spins, in degrees per second
+#[derive(Component)]
+pub struct AngularVelocity(pub f64);
+
+// What happens when an entity reaches the edge of the screen
+#[derive(Clone, Copy, PartialEq)]
+pub enum EdgePolicy {
+    Wrap, //Comes back in on the opposite side
+    Bounce, //Velocity is reflected off the edge
+    Despawn //Removed as soon as it is off screen
+}
+
+impl EdgePolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wrap" => Some(EdgePolicy::Wrap),
+            "bounce" => Some(EdgePolicy::Bounce),
+            "despawn" => Some(EdgePolicy::Despawn),
+            _ => None
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ScreenEdge(pub EdgePolicy);
+
+// Put on anything deleted during a tick. Deletes only land when the World is maintained,
+// so systems later in the same tick check for this to leave the entity alone
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub struct Despawned;
+
+// How long an entity has left before it is removed
+#[derive(Component)]
+pub enum Lifetime {
+    Time(f64), //Seconds
+    Distance(f64) //Pixels travelled
 }
 
 // Renderable Item and image deets
 #[derive(Component)]
 pub struct Renderable {
-    pub tex_name: String, //Texture name
+    pub texture: String, //Texture id from the asset manifest
     pub i_w: u32, //Image Width
     pub i_h: u32, //Image Height
     pub o_w: u32, //Output Width
@@ -23,33 +76,74 @@ pub struct Renderable {
     pub rot: f64 //Rotation of Image
 }
 
+// What an animation does once it reaches its last frame
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnimationMode {
+    Loop,
+    Once, //Stays on the last frame
+    OnceThenDespawn //Removes the entity, e.g. explosions
+}
+
+// Steps Renderable.frame through a sprite sheet.
+// Frames are i_w by i_h cells read left to right, top to bottom, `columns` to a row
+#[derive(Component)]
+pub struct Animation {
+    pub fps: f64,
+    pub mode: AnimationMode,
+    pub columns: u32,
+    pub elapsed: f64
+}
+
+impl Animation {
+    pub fn new(fps: f64, mode: AnimationMode, columns: u32) -> Self {
+        Self {
+            fps,
+            mode,
+            columns,
+            elapsed: 0.0
+        }
+    }
+}
+
+// Which group an entity collides as, ordered so the broadphase can look each pair up from one side only
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum CollisionLayer {
+    Player,
+    Missile,
+    Asteroid
+}
+
+// Circle used by the broadphase to find contacts
+#[derive(Component)]
+pub struct Collider {
+    pub radius: f64,
+    pub layer: CollisionLayer
+}
+
 // Player Component
 #[derive(Component)]
 pub struct Player {
     pub impulse: Vector2D<f64>,
-    pub cur_speed: Vector2D<f64>,
     pub lives: u32,
     pub died: bool,
+    pub thrusting: bool,
     pub invulnerable: bool
 }
 
 // Asteroid Component
 #[derive(Component)]
-pub struct Asteroid {
-    pub speed: f64,
-    pub rot_speed: f64
-}
+pub struct Asteroid;
 
 #[derive(Component)]
-pub struct Missile {
-    pub speed: f64
-}
+pub struct Missile;
 
 pub struct PendingAsteroid{
     pub x: f64,
     pub y: f64,
     pub rot: f64,
-    pub section: u32,
+    pub vel_x: f64,
+    pub vel_y: f64,
+    pub rot_speed: f64,
     pub size: u32
 }
 
@@ -57,7 +151,7 @@ pub struct PendingAsteroid{
 pub struct GameData{
     pub score: u32,
     pub level: u32,
-    pub showControls: bool
+    pub show_controls: bool
 }
 
 // #[derive(Component)]
@@ -65,16 +159,4 @@ pub struct GameData{
 //     pub size: u32
 // }
 
-#[derive(PartialEq)]
-pub enum SoundCueType{
-    PlaySound,
-    LoopSound,
-    StopSound
-}
-
-#[derive(Component)]
-pub struct SoundCue{
-    pub filename: String,
-    pub sc_type: SoundCueType
-}
 
@@ -0,0 +1,37 @@
+use sdl2::video::{FullscreenType, Window};
+
+// How the window is shown. The game always draws at the logical size and SDL scales and letterboxes it to fit
+#[derive(Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless, //Fullscreen at the desktop resolution
+    Fullscreen //Switches the display to the window's resolution
+}
+
+impl DisplayMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "windowed" => Some(DisplayMode::Windowed),
+            "borderless" => Some(DisplayMode::Borderless),
+            "fullscreen" => Some(DisplayMode::Fullscreen),
+            _ => None
+        }
+    }
+
+    // The mode F11 switches to
+    pub fn next(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed
+        }
+    }
+
+    pub fn apply(self, window: &mut Window) -> Result<(), String> {
+        window.set_fullscreen(match self {
+            DisplayMode::Windowed => FullscreenType::Off,
+            DisplayMode::Borderless => FullscreenType::Desktop,
+            DisplayMode::Fullscreen => FullscreenType::True
+        })
+    }
+}
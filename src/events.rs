@@ -0,0 +1,16 @@
+use specs::shrev::EventChannel;
+
+// Something that happened in the game this tick.
+// Systems publish these and anything interested (audio, scoring, ...) keeps its own ReaderId.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    MissileFired,
+    MissileExpired,
+    AsteroidDestroyed { x: f64, y: f64, size: u32 },
+    PlayerDied { lives_left: u32 },
+    LevelCleared { level: u32 },
+    ThrusterStarted,
+    ThrusterStopped
+}
+
+pub type GameEvents = EventChannel<GameEvent>;
@@ -3,42 +3,50 @@ use specs::{World, WorldExt, Builder, Join};
 
 use rand::Rng;
 
-use crate::{components, SCREEN_HEIGHT, SCREEN_WIDTH, SHOOT_FILENAME};
+use crate::components;
+use crate::events::{GameEvent, GameEvents};
 use crate::utils;
+use crate::physics;
+
+// Rolls for a spot away from the player before giving up and using the last one
+const MAX_SPAWN_ATTEMPTS: u32 = 100;
 
 const ROTATION_SPEED: f64 = 120.0;
 const IMPULSE_SPEED: f64 = 300.0;
-pub fn update(ecs: &mut World, key_manager: &mut HashMap<String,bool>, deltaTime: f64){
+pub fn update(ecs: &mut World, key_manager: &mut HashMap<String,bool>, delta_time: f64){
     //Check status of the game world
-    let mut must_reload_world = false;
-    let mut current_player_position = components::Position{x:0.0, y: 0.0, rot: 0.0, section: 0};
+    let mut game_over = false;
+    let mut current_player_position = components::Position{x:0.0, y: 0.0, rot: 0.0};
+    let respawn_point = ecs.read_resource::<physics::Playfield>().centre();
 
     {
         let mut players = ecs.write_storage::<crate::components::Player>();
         let mut positions = ecs.write_storage::<crate::components::Position>();
-        for(pos,player) in (&mut positions,&mut players).join(){
+        let mut velocities = ecs.write_storage::<crate::components::Velocity>();
+        for(pos,vel,player) in (&mut positions,&mut velocities,&mut players).join(){
             if player.died {
                 player.died = false;
                 player.lives -= 1;
-                player.cur_speed.x = 0.0;
-                player.cur_speed.y = 0.0;
+                vel.x = 0.0;
+                vel.y = 0.0;
                 player.impulse.x = 0.0;
                 player.impulse.y = 0.0;
-                pos.x = crate::SCREEN_WIDTH as f64 / 2.0;
-                pos.y = crate::SCREEN_HEIGHT as f64 / 2.0;
+                (pos.x, pos.y) = respawn_point;
             }
             current_player_position.x = pos.x;
             current_player_position.y = pos.y;
         }
         if players.join().count() < 1 {
-            must_reload_world = true;
+            game_over = true;
         }
 
     }
 
-    if must_reload_world {
-        ecs.delete_all();
-        load_world(ecs);
+    if game_over {
+        //The ship is gone so nothing is left to keep the thruster going.
+        //What happens next is up to whoever runs the simulation, see is_game_over
+        ecs.write_resource::<GameEvents>().single_write(GameEvent::ThrusterStopped);
+        return;
     }
 
     let mut must_create_asteroid = false;
@@ -49,7 +57,9 @@ pub fn update(ecs: &mut World, key_manager: &mut HashMap<String,bool>, deltaTime
             must_create_asteroid = true;
 
             let mut gamedatas = ecs.write_storage::<crate::components::GameData>();
-            for mut gamedata in (&mut gamedatas).join(){
+            let mut events = ecs.write_resource::<GameEvents>();
+            for gamedata in (&mut gamedatas).join(){
+                events.single_write(GameEvent::LevelCleared{level: gamedata.level});
                 gamedata.level += 1;
                 number_asteroids = (gamedata.level /3) + 1;
             }
@@ -57,48 +67,59 @@ pub fn update(ecs: &mut World, key_manager: &mut HashMap<String,bool>, deltaTime
     }
 
     if must_create_asteroid {
+        let playfield = *ecs.read_resource::<physics::Playfield>();
+        let margin = playfield.spawn_margin();
+        let clearance = playfield.spawn_clearance();
         let mut asteroid_count: u32 = 0;
+        let mut attempts: u32 = 0;
         while asteroid_count < number_asteroids {
-            let mut rng = rand::thread_rng();
-            let next_x = rng.gen_range(50.0..crate::SCREEN_WIDTH as f64 - 50.0);
-            let next_y = rng.gen_range(50.0..crate::SCREEN_HEIGHT as f64 - 50.0);
-            let next_rot = rng.gen_range(0.0..360.0);
+            let (next_x, next_y, next_rot) = {
+                let mut rng = ecs.write_resource::<crate::GameRng>();
+                (
+                    rng.0.gen_range(margin..playfield.width as f64 - margin),
+                    rng.0.gen_range(margin..playfield.height as f64 - margin),
+                    rng.0.gen_range(0.0..360.0)
+                )
+            };
 
             let diff_x = (next_x - current_player_position.x).abs();
             let diff_y = (next_y - current_player_position.y).abs();
-            let dist = (diff_x * diff_x + diff_y * diff_y);
-            if dist < 22500.0 {
+            let dist = diff_x * diff_x + diff_y * diff_y;
+            attempts += 1;
+            if dist < clearance * clearance && attempts < MAX_SPAWN_ATTEMPTS {
                 continue;
             }
 
             asteroid_count += 1;
+            attempts = 0;
             let new_asteroid = components::Position{
                 x: next_x,
                 y: next_y,
-                rot: next_rot,
-                section: 0
+                rot: next_rot
             };
             create_asteroid(ecs,new_asteroid,100);
         }
 
     }
 
-    let mut player_pos = components::Position{x: 0.0, y: 0.0, rot: 0.0,section: 0};
+    let mut player_pos = components::Position{x: 0.0, y: 0.0, rot: 0.0};
+    let mut player_vel = components::Velocity{x: 0.0, y: 0.0};
     let mut must_fire_missile = false;
     let mut thruster_pushed = false;
+    let mut thruster_changed = false;
     {
         let mut positions =  ecs.write_storage::<crate::components::Position>();
         let mut players = ecs.write_storage::<crate::components::Player>();
-        let mut renderables = ecs.write_storage::<crate::components::Renderable>();
+        let mut velocities = ecs.write_storage::<crate::components::Velocity>();
 
-        for(player,pos, renderable) in (&mut players, &mut positions, &mut renderables).join(){
+        for(player,pos, vel) in (&mut players, &mut positions, &mut velocities).join(){
 
-            if crate::utils::is_key_pressed(&key_manager, "D"){
-                pos.rot += ROTATION_SPEED * deltaTime;
+            if crate::utils::is_key_pressed(key_manager, "D"){
+                pos.rot += ROTATION_SPEED * delta_time;
                 thruster_pushed = true;
             }
-            if crate::utils::is_key_pressed(&key_manager, "A"){
-                pos.rot -= ROTATION_SPEED*deltaTime;
+            if crate::utils::is_key_pressed(key_manager, "A"){
+                pos.rot -= ROTATION_SPEED*delta_time;
                 thruster_pushed = true;
             }
             if pos.rot > 360.0 {
@@ -108,77 +129,61 @@ pub fn update(ecs: &mut World, key_manager: &mut HashMap<String,bool>, deltaTime
                 pos.rot += 360.0;
             }
 
-            if crate::utils::is_key_pressed(&key_manager, "W"){
+            if crate::utils::is_key_pressed(key_manager, "W"){
                 player.impulse.y -= pos.rot.to_radians().cos() * IMPULSE_SPEED;
                 player.impulse.x += pos.rot.to_radians().sin() * IMPULSE_SPEED;
                 thruster_pushed = true;
             }
-            update_movement(pos,player,deltaTime as f64);
+            update_movement(vel,player,delta_time);
 
-            if pos.x > crate::SCREEN_WIDTH.into() {
-                pos.x -= crate::SCREEN_WIDTH as f64;
-            }
-            else if pos.x < 0.0 {
-                pos.x += crate::SCREEN_WIDTH as f64;
+            if player.thrusting != thruster_pushed {
+                player.thrusting = thruster_pushed;
+                thruster_changed = true;
             }
 
-            if pos.y > crate::SCREEN_HEIGHT.into() {
-                pos.y -= crate::SCREEN_HEIGHT as f64;
-            }
-            else if pos.y < 0.0 {
-                pos.y += crate::SCREEN_HEIGHT as f64;
-            }
-
-            if utils::is_key_pressed(&key_manager, " "){
+            if utils::is_key_pressed(key_manager, " "){
                 utils::key_up(key_manager, " ".to_string());
                 must_fire_missile = true;
                 player_pos.x = pos.x;
                 player_pos.y = pos.y;
                 player_pos.rot = pos.rot;
+                player_vel.x = vel.x;
+                player_vel.y = vel.y;
             }
-            //Update the graphic to reflect the rotation
-            renderable.rot = pos.rot;
         }
     }
 
-    if thruster_pushed {
-        ecs.create_entity()
-            .with(components::SoundCue{
-                filename: crate::THRUSTER_FILENAME.to_string(),
-                sc_type: components::SoundCueType::LoopSound
-            })
-            .build();
-    }
-    else{
-        ecs.create_entity()
-            .with(components::SoundCue{
-                filename: crate::THRUSTER_FILENAME.to_string(),
-                sc_type: components::SoundCueType::StopSound
-            })
-            .build();
+    if thruster_changed {
+        let mut events = ecs.write_resource::<GameEvents>();
+        if thruster_pushed {
+            events.single_write(GameEvent::ThrusterStarted);
+        }
+        else{
+            events.single_write(GameEvent::ThrusterStopped);
+        }
     }
 
     if must_fire_missile {
-        fire_missile(ecs, player_pos);
+        fire_missile(ecs, player_pos, player_vel);
     }
 }
 
 const FRICTION: f64 = 2.5;
 const MAX_SPEED: f64 = 500.0;
-pub fn update_movement(pos: &mut crate::components::Position, player: &mut crate::components::Player, deltaTime: f64){
-    // player.cur_speed*=FRICTION;
-    player.cur_speed -= player.cur_speed * (FRICTION * deltaTime);
+// Applies friction and thrust to the ship's velocity, physics::Integrator does the moving
+pub fn update_movement(vel: &mut crate::components::Velocity, player: &mut crate::components::Player, delta_time: f64){
+    let mut cur_speed = vector2d::Vector2D::new(vel.x, vel.y);
+    // cur_speed*=FRICTION;
+    cur_speed -= cur_speed * (FRICTION * delta_time);
 
-    player.cur_speed+=player.impulse;
+    cur_speed+=player.impulse;
 
-    if player.cur_speed.length() > MAX_SPEED {
-        player.cur_speed *= MAX_SPEED/player.cur_speed.length();
+    if cur_speed.length() > MAX_SPEED {
+        cur_speed *= MAX_SPEED/cur_speed.length();
     }
 
-    pos.x += player.cur_speed.x * deltaTime;
-    pos.y += player.cur_speed.y * deltaTime;
-
-    pos.section = (pos.x as u32/ crate::SECTION_WIDTH) * crate::NO_OF_SECTIONS +  (pos.y as u32 / crate::SECTION_HEIGHT);
+    vel.x = cur_speed.x;
+    vel.y = cur_speed.y;
 
     player.impulse = vector2d::Vector2D::new(0.0,0.0);
 }
@@ -186,10 +191,13 @@ pub fn update_movement(pos: &mut crate::components::Position, player: &mut crate
 // pub const MAX_STARS: u32 = 100;
 
 pub fn load_world(ecs: &mut World){
+    let playfield = *ecs.read_resource::<physics::Playfield>();
+    let edges = playfield.edges;
+    let (centre_x, centre_y) = playfield.centre();
     ecs.create_entity()
-        .with(crate::components::Position{x: (SCREEN_WIDTH/2) as f64, y: (SCREEN_HEIGHT/2) as f64, rot: 0.0, section: 0})
+        .with(crate::components::Position{x: centre_x, y: centre_y, rot: 0.0})
         .with(crate:: components::Renderable{
-            tex_name: String::from("img/ship.png"),
+            texture: String::from("ship"),
             i_w: 100,
             i_h: 100,
             o_w: 50,
@@ -198,27 +206,36 @@ pub fn load_world(ecs: &mut World){
             total_frames: 1,
             rot: 0.0
         })
+        .with(crate::components::Collider{
+            radius: 25.0,
+            layer: components::CollisionLayer::Player
+        })
+        .with(crate::components::Velocity{x: 0.0, y: 0.0})
+        .with(crate::components::ScreenEdge(edges.player))
         .with(crate::components::Player{
             impulse: vector2d::Vector2D::new(0.0,0.0),
-            cur_speed: vector2d::Vector2D::new(0.0,0.0),
             lives: 3,
             died: false,
+            thrusting: false,
             invulnerable: false
         })
         .build();
 
-    create_asteroid(ecs, components::Position{x: 400.0, y: 235.0, rot: 45.0, section: 0},50);
+    //The same spot relative to the playfield as (400, 235) on a full screen
+    let first_x = playfield.width as f64 * 400.0 / crate::SCREEN_WIDTH as f64;
+    let first_y = playfield.height as f64 * 235.0 / crate::SCREEN_HEIGHT as f64;
+    create_asteroid(ecs, components::Position{x: first_x, y: first_y, rot: 45.0},50);
 
     ecs.create_entity()
         .with(crate::components::GameData{
             score: 0,
             level: 1,
-            showControls: false
+            show_controls: false
         })
         .build();
 
     // for _ in 0..MAX_STARS { //Create Stars
-    //     let mut rng = rand::thread_rng();
+    //     let mut rng = ecs.write_resource::<crate::GameRng>();
     //     let next_x = rng.gen_range(0.0..crate::SCREEN_WIDTH as f64);
     //     let next_y = rng.gen_range(0.0..crate::SCREEN_HEIGHT as f64);
     //     let next_size = rng.gen_range(1..4);
@@ -236,8 +253,11 @@ pub fn load_world(ecs: &mut World){
 }
 
 const MAX_MISSILES: usize = 5;
+const MISSILE_SPEED: f64 = 600.0;
+const MISSILE_RANGE: f64 = 900.0; //Pixels a missile travels before it fizzles out
 
-fn fire_missile(ecs: &mut World, position: components::Position){
+// Missiles leave the ship at MISSILE_SPEED on top of whatever speed the ship already had
+fn fire_missile(ecs: &mut World, position: components::Position, ship_velocity: components::Velocity){
     {
         let missiles = ecs.read_storage::<crate::components::Missile>();
         if missiles.count() > MAX_MISSILES - 1{
@@ -245,10 +265,13 @@ fn fire_missile(ecs: &mut World, position: components::Position){
         }
     }
 
+    let launch = physics::velocity_from_heading(position.rot, MISSILE_SPEED);
+    let edges = ecs.read_resource::<physics::Playfield>().edges;
     ecs.create_entity()
         .with(position)
+        .with(crate::components::Velocity{x: launch.x + ship_velocity.x, y: launch.y + ship_velocity.y})
         .with(crate::components::Renderable{
-            tex_name: String::from("img/missile.png"),
+            texture: String::from("missile"),
             i_w: 100,
             i_h: 100,
             o_w: 25,
@@ -257,24 +280,30 @@ fn fire_missile(ecs: &mut World, position: components::Position){
             total_frames: 1,
             rot: 0.0
         })
-        .with(crate::components::Missile{
-            speed: 600.0
+        .with(crate::components::Collider{
+            radius: 0.0,
+            layer: components::CollisionLayer::Missile
         })
+        .with(crate::components::ScreenEdge(edges.missile))
+        .with(crate::components::Lifetime::Distance(MISSILE_RANGE))
+        .with(crate::components::Missile)
         .build();
 
-    ecs.create_entity()
-        .with(components::SoundCue{
-            filename: crate::SHOOT_FILENAME.to_string(),
-            sc_type: components::SoundCueType::PlaySound
-        })
-        .build();
+    ecs.write_resource::<GameEvents>().single_write(GameEvent::MissileFired);
 }
 
+const ASTEROID_SPEED: f64 = 150.0;
+const ASTEROID_ROT_SPEED: f64 = 150.0;
+
+// New asteroids set off in the direction they are facing
 pub fn create_asteroid(ecs: &mut World, position: components::Position, asteroid_size: u32){
+    let edges = ecs.read_resource::<physics::Playfield>().edges;
     ecs.create_entity()
+        .with(physics::velocity_from_heading(position.rot, ASTEROID_SPEED))
+        .with(components::AngularVelocity(ASTEROID_ROT_SPEED))
         .with(position)
         .with(crate::components::Renderable{
-            tex_name: String::from("img/asteroid1.png"),
+            texture: String::from("asteroid"),
             i_w: 100,
             i_h: 100,
             o_w: asteroid_size,
@@ -283,38 +312,67 @@ pub fn create_asteroid(ecs: &mut World, position: components::Position, asteroid
             total_frames: 1,
             rot: 0.0
         })
-        .with(crate::components::Asteroid{
-            speed: 150.0,
-            rot_speed: 150.0
+        .with(crate::components::Collider{
+            radius: asteroid_size as f64 / 2.0,
+            layer: components::CollisionLayer::Asteroid
         })
+        .with(crate::components::ScreenEdge(edges.asteroid))
+        .with(crate::components::Asteroid)
         .build();
 }
 
 pub fn toggle_invincibility(ecs: &mut World){
     let mut players = ecs.write_storage::<crate::components::Player>();
-    for mut player in (&mut players).join(){
+    for player in (&mut players).join(){
         player.invulnerable = !player.invulnerable;
     }
 }
 
 pub fn create_thousand_asteroids(ecs: &mut World){
-    let mut rng = rand::thread_rng();
-    for i in 0..1000 {
-        let x = rng.gen_range(50.0..crate::SCREEN_WIDTH as f64 - 50.0);
-        let y = rng.gen_range(50.0..crate::SCREEN_HEIGHT as f64 - 50.0);
-        let rot = rng.gen_range(0.0..360.0);
-        create_asteroid(ecs, components::Position{x, y, rot ,section:0}, 50);
+    create_random_asteroids(ecs, 1000);
+}
+
+pub fn create_random_asteroids(ecs: &mut World, count: u32){
+    let mut new_asteroids = Vec::<components::Position>::new();
+    {
+        let playfield = *ecs.read_resource::<physics::Playfield>();
+        let margin = playfield.spawn_margin();
+        let mut rng = ecs.write_resource::<crate::GameRng>();
+        for _ in 0..count {
+            let x = rng.0.gen_range(margin..playfield.width as f64 - margin);
+            let y = rng.0.gen_range(margin..playfield.height as f64 - margin);
+            let rot = rng.0.gen_range(0.0..360.0);
+            new_asteroids.push(components::Position{x, y, rot});
+        }
+    }
+    for position in new_asteroids {
+        create_asteroid(ecs, position, 50);
     }
 }
 
 pub fn toggle_show_controls(ecs: &mut World){
     let mut gamedatas = ecs.write_storage::<crate::components::GameData>();
-    for mut gamedata in (&mut gamedatas).join(){
-        gamedata.showControls = !gamedata.showControls;
+    for gamedata in (&mut gamedatas).join(){
+        gamedata.show_controls = !gamedata.show_controls;
     }
 }
 
 pub fn get_asteroid_count(ecs: &World) -> u32{
-    let mut asteroids = ecs.read_storage::<crate::components::Asteroid>();
+    let asteroids = ecs.read_storage::<crate::components::Asteroid>();
     asteroids.join().count() as u32
-}
\ No newline at end of file
+}
+
+pub fn get_score(ecs: &World) -> u32{
+    let gamedatas = ecs.read_storage::<crate::components::GameData>();
+    gamedatas.join().map(|gamedata| gamedata.score).next().unwrap_or(0)
+}
+
+pub fn is_thrusting(ecs: &World) -> bool{
+    let players = ecs.read_storage::<crate::components::Player>();
+    players.join().any(|player| player.thrusting)
+}
+
+pub fn is_game_over(ecs: &World) -> bool{
+    let players = ecs.read_storage::<crate::components::Player>();
+    players.join().count() < 1
+}
@@ -0,0 +1,127 @@
+use std::fs;
+
+use serde::Deserialize;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use sdl2::ttf::Font;
+
+use crate::assets::AssetError;
+use crate::text::TextCache;
+
+// Used when the layout file can't be read so the HUD never disappears
+const BUILTIN_LAYOUT: &str = include_str!("../hud.toml");
+
+// Where on the screen an element is measured from
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    Left,
+    Center,
+    Right
+}
+
+// One piece of HUD text or a panel such as the minimap, looked up by id from render
+#[derive(Deserialize)]
+pub struct HudElement {
+    pub id: String,
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub align: Option<Align>, //Defaults to the side the anchor is on
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub width: Option<u32>, //Panels only, text is as wide as its string
+    pub height: u32 //Text height in pixels, the width comes from the string
+}
+
+#[derive(Deserialize)]
+pub struct HudLayout {
+    #[serde(default)]
+    pub padding: i32, //Gap kept between the screen edges and anchored elements
+    #[serde(rename = "element", default)]
+    elements: Vec<HudElement>
+}
+
+impl HudLayout {
+    pub fn load(path: &str) -> Result<Self, AssetError> {
+        let text = fs::read_to_string(path).map_err(|e| AssetError::from_load(path, e))?;
+        toml::from_str(&text).map_err(|e| AssetError::Decode { path: path.to_string(), reason: e.to_string() })
+    }
+
+    pub fn builtin() -> Self {
+        toml::from_str(BUILTIN_LAYOUT).expect("Built in HUD layout is invalid")
+    }
+
+    fn element(&self, id: &str) -> Option<&HudElement> {
+        self.elements.iter().find(|element| element.id == id)
+    }
+
+    // Screen rectangle for an element holding text of the given natural size
+    pub fn place(&self, id: &str, text_size: (u32, u32), screen: (u32, u32)) -> Option<Rect> {
+        let element = self.element(id)?;
+        let (text_w, text_h) = text_size;
+        let width = (text_w as u64 * element.height as u64 / text_h.max(1) as u64) as u32;
+        Some(self.position(element, width, screen))
+    }
+
+    // Screen rectangle for a panel, which has a fixed width in the layout instead of a string to measure
+    pub fn panel(&self, canvas: &WindowCanvas, id: &str) -> Option<Rect> {
+        let element = self.element(id)?;
+        Some(self.position(element, element.width?, screen_size(canvas)))
+    }
+
+    fn position(&self, element: &HudElement, width: u32, screen: (u32, u32)) -> Rect {
+        let height = element.height;
+        let (screen_w, screen_h) = (screen.0 as i32, screen.1 as i32);
+        let (anchor_x, default_align) = match element.anchor {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => (self.padding, Align::Left),
+            Anchor::Top | Anchor::Center | Anchor::Bottom => (screen_w / 2, Align::Center),
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => (screen_w - self.padding, Align::Right)
+        };
+        let top = match element.anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => self.padding,
+            Anchor::Left | Anchor::Center | Anchor::Right => (screen_h - height as i32) / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => screen_h - self.padding - height as i32
+        };
+        let left = match element.align.unwrap_or(default_align) {
+            Align::Left => anchor_x,
+            Align::Center => anchor_x - width as i32 / 2,
+            Align::Right => anchor_x - width as i32
+        };
+
+        Rect::new(left + element.x, top + element.y, width.max(1), height.max(1))
+    }
+
+    // Measures the text with the font and draws it where the layout says, elements not in the layout are skipped
+    pub fn draw(&self, canvas: &mut WindowCanvas, font: &Font, text_cache: &mut TextCache, id: &'static str, text: &str) -> Result<(), String> {
+        let text_size = font.size_of(text).map_err(|e| e.to_string())?;
+        match self.place(id, text_size, screen_size(canvas)) {
+            Some(target) => text_cache.draw(canvas, font, id, text, target),
+            None => Ok(())
+        }
+    }
+}
+
+// The area the HUD is laid out in, the logical size once one is set
+fn screen_size(canvas: &WindowCanvas) -> (u32, u32) {
+    match canvas.logical_size() {
+        (0, 0) => canvas.output_size().unwrap_or((crate::SCREEN_WIDTH, crate::SCREEN_HEIGHT)),
+        size => size
+    }
+}
@@ -0,0 +1,32 @@
+// The game simulation: components, systems, replays and the headless runners.
+// None of it touches SDL or kira, so it builds and runs on machines without them, see src/bin/headless.rs
+use rand::rngs::StdRng;
+
+pub mod utils;
+pub mod components;
+pub mod game;
+pub mod asteroid;
+pub mod missile;
+pub mod simulation;
+pub mod replay;
+pub mod collision;
+pub mod physics;
+pub mod lifetime;
+pub mod events;
+pub mod scoring;
+pub mod animation;
+pub mod camera;
+pub mod cli;
+
+pub const SCREEN_WIDTH: u32 = 1920;
+pub const SCREEN_HEIGHT: u32 = 1080;
+pub const GRID_CELL_SIZE: u32 = 128; //Spatial grid cell size, see collision.rs
+
+#[derive(Default)]
+pub struct DeltaTime(pub f64);
+
+// Every random roll in the game comes from here so a seed reproduces a run
+pub struct GameRng(pub StdRng);
+
+// The seed GameRng was started with, kept around for the HUD and replays
+pub struct Seed(pub u64);
@@ -0,0 +1,96 @@
+use specs::{System, WriteStorage, ReadStorage, Join, LendJoin, Read, Write};
+use specs::prelude::Entities;
+
+use crate::components;
+use crate::events::{GameEvent, GameEvents};
+
+// Counts down every Lifetime and removes whatever runs out
+pub struct LifetimeTicker;
+
+impl<'a> System<'a> for LifetimeTicker {
+    type SystemData = (
+        WriteStorage<'a, components::Lifetime>,
+        ReadStorage<'a, components::Velocity>,
+        ReadStorage<'a, components::Missile>,
+        WriteStorage<'a, components::Despawned>,
+        Write<'a, GameEvents>,
+        Entities<'a>,
+        Read<'a, crate::DeltaTime>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut lifetimes, velocities, missiles, mut despawned, mut events, entities, deltatime) = data;
+        let deltatime = deltatime.0;
+
+        let mut expired = Vec::new();
+        for (lifetime, vel, entity) in (&mut lifetimes, velocities.maybe(), &entities).join() {
+            let remaining = match lifetime {
+                components::Lifetime::Time(seconds) => {
+                    *seconds -= deltatime;
+                    *seconds
+                },
+                components::Lifetime::Distance(pixels) => {
+                    if let Some(vel) = vel {
+                        *pixels -= (vel.x * vel.x + vel.y * vel.y).sqrt() * deltatime;
+                    }
+                    *pixels
+                }
+            };
+            if remaining <= 0.0 {
+                entities.delete(entity).ok();
+                despawned.insert(entity, components::Despawned).ok();
+                expired.push(entity);
+            }
+        }
+
+        //A missile running out of range frees up a shot
+        for entity in expired {
+            if missiles.get(entity).is_some() {
+                events.single_write(GameEvent::MissileExpired);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, RunNow, World, WorldExt};
+    use crate::collision::{Broadphase, CollisionRules, Contacts, SpatialGrid};
+    use crate::components::CollisionLayer;
+    use crate::physics::Playfield;
+
+    #[test]
+    fn an_expired_missile_hits_nothing_on_its_last_tick() {
+        let playfield = Playfield::default();
+        let mut world = World::new();
+        world.register::<components::Position>();
+        world.register::<components::Collider>();
+        world.register::<components::ScreenEdge>();
+        world.register::<components::Lifetime>();
+        world.register::<components::Velocity>();
+        world.register::<components::Missile>();
+        world.register::<components::Despawned>();
+        world.insert(SpatialGrid::new(playfield.width, playfield.height, playfield.grid_cell_size));
+        world.insert(Contacts::default());
+        world.insert(CollisionRules::default());
+        world.insert(GameEvents::new());
+        world.insert(crate::DeltaTime(0.1));
+        world.insert(playfield);
+
+        world.create_entity()
+            .with(components::Position { x: 500.0, y: 500.0, rot: 0.0 })
+            .with(components::Collider { radius: 10.0, layer: CollisionLayer::Missile })
+            .with(components::Lifetime::Time(0.05))
+            .with(components::Missile)
+            .build();
+        world.create_entity()
+            .with(components::Position { x: 505.0, y: 500.0, rot: 0.0 })
+            .with(components::Collider { radius: 50.0, layer: CollisionLayer::Asteroid })
+            .build();
+
+        LifetimeTicker.run_now(&world);
+        Broadphase.run_now(&world);
+        assert!(world.read_resource::<Contacts>().0.is_empty());
+    }
+}
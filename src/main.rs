@@ -1,44 +1,64 @@
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::render::{WindowCanvas, TextureCreator};
+use sdl2::render::WindowCanvas;
 use sdl2::video::WindowContext;
 use sdl2::pixels::Color;
 use sdl2::rect::{Rect,Point};
-use specs::{World, WorldExt, Join, DispatcherBuilder};
+use specs::{World, WorldExt, Join, LendJoin};
 use std::time::Instant;
 
-use std::sync::Mutex;
 use std::time::Duration;
 use std::path::Path;
 use std::collections::HashMap;
-use kira::track::effect::delay::DelayBuilder;
 
-use once_cell::sync::Lazy;
 
 pub mod texture_manager;
 pub mod sound_manager;
-pub mod utils;
-pub mod components;
-pub mod game;
-pub mod asteroid;
-pub mod missile;
-
-
-const SCREEN_WIDTH: u32 = 1920;
-const SCREEN_HEIGHT: u32 = 1080;
-const NO_OF_SECTIONS: u32 = 4;
-const SECTION_WIDTH: u32 = SCREEN_WIDTH/NO_OF_SECTIONS;
-const SECTION_HEIGHT: u32 = SCREEN_HEIGHT/NO_OF_SECTIONS;
-
-const MUSIC_FILENAME: &str = "sounds/music/space_ranger.wav";
-const THRUSTER_FILENAME: &str = "sounds/fx/thrusters.mp3";
-const SHOOT_FILENAME: &str = "sounds/fx/shoot.mp3";
-const RELOAD_FILENAME: &str = "sounds/fx/reload.wav";
+pub mod assets;
+pub mod text;
+pub mod hud;
+pub mod display;
+pub mod minimap;
+pub mod state;
+
+//The simulation lives in the library so it can be built and run without SDL, see lib.rs
+use asteroids_assignment::{components, game, simulation, replay, physics, events, camera, collision, utils, cli};
+use asteroids_assignment::{Seed, SCREEN_WIDTH, SCREEN_HEIGHT};
+use asteroids_assignment::scoring::GAMESTATE;
+
+//Asset ids, the files behind them are listed in the manifest
+const ASSET_MANIFEST: &str = "assets.toml";
+const PRELOAD_GROUP: &str = "core";
+const MUSIC_SOUND: &str = "music";
+const THRUSTER_SOUND: &str = "thruster";
+const SHOOT_SOUND: &str = "shoot";
+const RELOAD_SOUND: &str = "reload";
+const HUD_FONT: &str = "hud";
+const HUD_LAYOUT: &str = "hud.toml";
+
+// Blends between the previous and current tick, snapping instead when the entity wrapped around the playfield
+fn interpolate_position(pos: &components::Position, prev: Option<&components::PreviousPosition>, playfield: &physics::Playfield, alpha: f64) -> (f64, f64) {
+    match prev {
+        Some(prev) if (pos.x - prev.x).abs() < (playfield.width / 2) as f64
+            && (pos.y - prev.y).abs() < (playfield.height / 2) as f64 => {
+            (prev.x + (pos.x - prev.x) * alpha, prev.y + (pos.y - prev.y) * alpha)
+        },
+        _ => (pos.x, pos.y)
+    }
+}
 
-#[derive(Default)]
-pub struct DeltaTime(pub f64);
+// Rotation between the last two ticks, turning the short way so 359 to 1 degrees doesn't spin all the way round
+fn interpolate_rotation(pos: &components::Position, prev: Option<&components::PreviousPosition>, alpha: f64) -> f64 {
+    match prev {
+        Some(prev) => {
+            let turned = (pos.rot - prev.rot + 180.0).rem_euclid(360.0) - 180.0;
+            prev.rot + turned * alpha
+        },
+        None => pos.rot
+    }
+}
 
-fn render(canvas: &mut WindowCanvas, texture_manager: &mut texture_manager::TextureManager<WindowContext>, texture_creator: &TextureCreator<WindowContext>, font: &sdl2::ttf::Font, ecs: &World, fps: u64) -> Result<(),String> {
+fn render(canvas: &mut WindowCanvas, texture_manager: &mut texture_manager::TextureManager<WindowContext>, text_cache: &mut text::TextCache, hud: &hud::HudLayout, font: &sdl2::ttf::Font, manifest: &assets::AssetManifest, ecs: &World, fps: u64, alpha: f64) -> Result<(),String> {
     let color = Color::RGB(255,255,255);
     canvas.set_draw_color(color);
     canvas.clear();
@@ -46,85 +66,97 @@ fn render(canvas: &mut WindowCanvas, texture_manager: &mut texture_manager::Text
    
 
     let positions = ecs.read_storage::<components::Position>();
-    {
-        
-    }
+    let previous_positions = ecs.read_storage::<components::PreviousPosition>();
 
     let renderables = ecs.read_storage::<components::Renderable>();
 
-    for(renderable, pos) in (&renderables,&positions).join(){
-        let src = Rect::new(0,0,renderable.i_w,renderable.i_h);
-        let x = pos.x as i32;
-        let y = pos.y as i32;
-        let dest = Rect::new(x - ((renderable.o_w/2) as i32), y - ((renderable.o_h/2) as i32),renderable.o_w,renderable.o_h);
-
-        let center = Point::new((renderable.o_w/2) as i32,(renderable.o_h/2) as i32);
-        let texture = texture_manager.load(&renderable.tex_name)?;
-        canvas.copy_ex(
-            &texture, //Texture Object
-            src, //Source Rectangle
-            dest, //Destination Rectangle
-            renderable.rot, //Rotation
-            center, //Rotation Center
-            false, //Flip Horizontal
-            false //Flip Vertical
-        )?;
-    }
+    let screen_edges = ecs.read_storage::<components::ScreenEdge>();
+    let animations = ecs.read_storage::<components::Animation>();
+    let playfield = *ecs.read_resource::<physics::Playfield>();
+    let (field_w, field_h) = (playfield.width as i32, playfield.height as i32);
 
+    //The camera keeps the player in view, drawn where they are between ticks so it doesn't judder
     let players = ecs.read_storage::<components::Player>();
-    for(renderable, pos, player) in (&renderables, &positions, &players).join(){
+    let mut camera = ecs.write_resource::<camera::Camera>();
+    let mut player_at = None;
+    for (pos, prev, _) in (&positions, previous_positions.maybe(), &players).join() {
+        let at = interpolate_position(pos, prev, &playfield, alpha);
+        camera.follow(at, &playfield);
+        player_at = Some(at);
+    }
+    let view = Rect::new(0, 0, camera.view_width, camera.view_height);
+
+    for(renderable, pos, prev, edge, animation) in (&renderables,&positions,previous_positions.maybe(),screen_edges.maybe(),animations.maybe()).join(){
+        //Pick the current frame's cell out of the sprite sheet, sheets without an Animation are one row
+        let columns = animation.map_or(renderable.total_frames, |animation| animation.columns).max(1);
+        let src = Rect::new(
+            ((renderable.frame % columns) * renderable.i_w) as i32,
+            ((renderable.frame / columns) * renderable.i_h) as i32,
+            renderable.i_w,
+            renderable.i_h
+        );
+        let (x, y) = interpolate_position(pos, prev, &playfield, alpha);
+        let rot = interpolate_rotation(pos, prev, alpha);
+        let (x, y) = camera.world_to_screen(x, y);
+        let x = x as i32;
+        let y = y as i32;
+        let dest = Rect::new(x - ((renderable.o_w/2) as i32), y - ((renderable.o_h/2) as i32),renderable.o_w,renderable.o_h);
 
-        //Show Lives
-        let lives: String = "Lives: ".to_string() + &player.lives.to_string();
-        let surface = font
-            .render(&lives)
-            .blended(Color::RGBA(0,0,0,255))
-            .map_err(|e| e.to_string())?;
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)
-            .map_err(|e| e.to_string())?;
-
-        let target = Rect::new((SCREEN_WIDTH - 135) as i32,10 as i32,125 as u32,50 as u32);
-        canvas.copy(&texture, None, Some(target));
-
-        let src = Rect::new(0,0,renderable.i_w, renderable.i_h);
-        let x: i32 = pos.x as i32;
-        let y: i32 = pos.y as i32;
-        let mut dest = Rect::new(x - ((renderable.o_w/2) as i32), y - ((renderable.o_h/2) as i32),renderable.o_w,renderable.o_h);
-
-        let mut draw_second = false;
-        if dest.x < (renderable.o_w/2).try_into().unwrap() {
-            dest.x += crate::SCREEN_WIDTH as i32;
-            draw_second = true;
-        } else if dest.x > (crate::SCREEN_WIDTH - renderable.o_w/2) as i32 {
-            dest.x -= crate::SCREEN_WIDTH as i32;
-            draw_second = true;
+        //Anything that wraps also gets drawn from the far side of the playfield, then whatever is off screen is culled
+        let (x_offsets, y_offsets): (&[i32], &[i32]) = if edge.map_or(false, |edge| edge.0 == components::EdgePolicy::Wrap) {
+            (&[0, field_w, -field_w], &[0, field_h, -field_h])
+        } else {
+            (&[0], &[0])
+        };
+        let mut copies = Vec::new();
+        for offset_x in x_offsets {
+            for offset_y in y_offsets {
+                let mut copy_dest = dest;
+                copy_dest.offset(*offset_x, *offset_y);
+                if copy_dest.has_intersection(view) {
+                    copies.push(copy_dest);
+                }
+            }
+        }
+        if copies.is_empty() {
+            continue;
         }
 
-        if dest.y < (renderable.o_h/2).try_into().unwrap() {
-            dest.y += crate::SCREEN_HEIGHT as i32;
-            draw_second = true;
-        } else if dest.y > (crate::SCREEN_HEIGHT - renderable.o_h/2) as i32 {
-            dest.y -= crate::SCREEN_HEIGHT as i32;
-            draw_second = true;
+        let center = Point::new((renderable.o_w/2) as i32,(renderable.o_h/2) as i32);
+        //An id missing from the manifest gets tried as a path, fails once and then shows the placeholder
+        let path = manifest.path(&renderable.texture).unwrap_or(&renderable.texture);
+        let texture = match texture_manager.load(path) {
+            Ok(texture) => texture,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
+        for copy_dest in copies {
+            canvas.copy_ex(
+                &texture, //Texture Object
+                src, //Source Rectangle
+                copy_dest, //Destination Rectangle
+                rot, //Rotation
+                center, //Rotation Center
+                false, //Flip Horizontal
+                false //Flip Vertical
+            )?;
         }
+    }
 
-        if !draw_second {
-            break;
+    //Only worth showing when there is more playfield than fits on screen
+    if let (Some(player_at), Some(area)) = (player_at, hud.panel(canvas, "minimap")) {
+        if camera.scrolls(&playfield) {
+            minimap::draw(canvas, area, ecs, &camera, &playfield, player_at)?;
         }
+    }
 
-        let center = Point::new((renderable.o_w/2) as i32,(renderable.o_h/2) as i32);
-        let texture = texture_manager.load(&renderable.tex_name)?;
-        canvas.copy_ex(
-            &texture, //Texture Object
-            src, //Source Rectangle
-            dest, //Destination Rectangle
-            renderable.rot, //Rotation
-            center, //Rotation Center
-            false, //Flip Horizontal
-            false //Flip Vertical
-        )?;
+    for player in (&players).join(){
 
+        //Show Lives
+        let lives: String = "Lives: ".to_string() + &player.lives.to_string();
+        hud.draw(canvas, font, text_cache, "lives", &lives)?;
     }
 
 
@@ -134,264 +166,191 @@ fn render(canvas: &mut WindowCanvas, texture_manager: &mut texture_manager::Text
         if &GAMESTATE.lock().unwrap().highscore > &gamedata.score {
             //Show Score
             let score: String = "Score: ".to_string() + &gamedata.score.to_string();
-            let surface = font
-                .render(&score)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-
-            let target = Rect::new(10 as i32,0 as i32,125 as u32,50 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "score", &score)?;
 
             //Show Highscore
-            let highscore: String = "High Score: ".to_string() + &GAMESTATE.lock().unwrap().highscore.to_string();
-            let surface = font
-                .render(&highscore)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new(10 as i32,50 as i32,150 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            let gamestate = GAMESTATE.lock().unwrap();
+            let mut highscore: String = "High Score: ".to_string() + &gamestate.highscore.to_string();
+            if !gamestate.highscore_name.is_empty() {
+                highscore = highscore + " " + &gamestate.highscore_name;
+            }
+            hud.draw(canvas, font, text_cache, "highscore", &highscore)?;
 
         }
         else{
             //Show Score (High)
             let highscore: String = "Score: ".to_string() + &GAMESTATE.lock().unwrap().highscore.to_string() + " High!";
-            let surface = font
-                .render(&highscore)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-
-            let target = Rect::new(10 as i32,0 as i32,200 as u32,50 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "score", &highscore)?;
         }
 
         //Show Level
         let level: String = "Level: ".to_string() + &gamedata.level.to_string();
-        let surface = font
-            .render(&level)
-            .blended(Color::RGBA(0,0,0,255))
-            .map_err(|e| e.to_string())?;
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)
-            .map_err(|e| e.to_string())?;
-
-        let target = Rect::new(10 as i32,(SCREEN_HEIGHT - 60 ) as i32,150 as u32,50 as u32);
-        canvas.copy(&texture, None, Some(target));
-
-        if gamedata.showControls {
+        hud.draw(canvas, font, text_cache, "level", &level)?;
+
+        //Show Seed
+        let seed: String = "Seed: ".to_string() + &ecs.read_resource::<Seed>().0.to_string();
+        hud.draw(canvas, font, text_cache, "seed", &seed)?;
+
+        if gamedata.show_controls {
             //Show Controls
             let moveControls: String = "WASD Move".to_string();
-            let surface = font
-                .render(&moveControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 185) as i32,(SCREEN_HEIGHT - 245 ) as i32,175 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "move_controls", &moveControls)?;
 
             let spaceControls: String = "Space Shoot".to_string();
-            let surface = font
-                .render(&spaceControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 210) as i32,(SCREEN_HEIGHT - 205 ) as i32,200 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
-
-            let MusicControls: String = "P Un/Pause Music".to_string();
-            let surface = font
-                .render(&MusicControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-
-            let target = Rect::new((SCREEN_WIDTH - 260) as i32,(SCREEN_HEIGHT - 165 ) as i32,250 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "space_controls", &spaceControls)?;
 
-            let InvincibleControls: String = "I Invincible".to_string();
-            let surface = font
-                .render(&InvincibleControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
+            let MusicControls: String = "P Pause  M Music".to_string();
+            hud.draw(canvas, font, text_cache, "music_controls", &MusicControls)?;
 
-            let target = Rect::new((SCREEN_WIDTH - 210) as i32,(SCREEN_HEIGHT - 125 ) as i32,200 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            let InvincibleControls: String = "I Invincible".to_string();
+            hud.draw(canvas, font, text_cache, "invincible_controls", &InvincibleControls)?;
 
 
             let AsteroidControls: String = "O 1000 Asteroids".to_string();
-            let surface = font
-                .render(&AsteroidControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 260) as i32,(SCREEN_HEIGHT - 85 ) as i32,250 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "asteroid_controls", &AsteroidControls)?;
 
             let FPSControls: String = "U Unlock FPS".to_string();
-            let surface = font
-                .render(&FPSControls)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 210) as i32,(SCREEN_HEIGHT - 45 ) as i32,200 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "fps_controls", &FPSControls)?;
 
 
 
             //Show Asteroids
             let asteroidCounter: String = "Asteroids: ".to_string() + &game::get_asteroid_count(&ecs).to_string() + " ";
 
-            let surface = font
-                .render(&asteroidCounter)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 480) as i32,(SCREEN_HEIGHT - 85 ) as i32,225 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "asteroid_counter", &asteroidCounter)?;
 
             //Show FPS
             let fpsCounter: String = "FPS: ".to_string() + &fps.to_string();
-            let surface = font
-                .render(&fpsCounter)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 480) as i32,(SCREEN_HEIGHT - 45 ) as i32,125 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "fps_counter", &fpsCounter)?;
         }
         else{
             //Show Info Control
             let info: String = "H Show Info".to_string();
-            let surface = font
-                .render(&info)
-                .blended(Color::RGBA(0,0,0,255))
-                .map_err(|e| e.to_string())?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
-            let target = Rect::new((SCREEN_WIDTH - 185) as i32,(SCREEN_HEIGHT - 45 ) as i32,175 as u32,35 as u32);
-            canvas.copy(&texture, None, Some(target));
+            hud.draw(canvas, font, text_cache, "info", &info)?;
         }
     }
 
-    canvas.present();
     Ok(())
 }
 
-struct State{ecs: World}
-
-pub struct GameState{
-    highscore: u32
-}
-
-static GAMESTATE: Lazy<Mutex<GameState>> = Lazy::new(|| {
-    Mutex::new(GameState{
-        highscore: 0
-    })
-});
-
 fn main() -> Result<(),String>{
     println!("Starting Asteroids!");
+
+    let args: Vec<String> = std::env::args().collect();
+    let (settings, loaded_replay) = cli::settings_from_args(&args)?;
+    println!("Seed: {}", settings.seed);
+
+    //The same as the headless binary, kept here so `--headless` keeps working from the game itself
+    if args.iter().any(|arg| arg == "--headless") {
+        return cli::run_headless(&args, &settings, loaded_replay);
+    }
     
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
     
-    let window = video_subsystem.window("Asteroids",SCREEN_WIDTH,SCREEN_HEIGHT)
-        .fullscreen()
+    //Pick how the window is shown, e.g. `--window windowed --window-size 1280x720`
+    let display_mode = match cli::arg_value(&args, "--window") {
+        Some(name) => display::DisplayMode::from_name(name)
+            .ok_or_else(|| format!("Unknown window mode {}, expected windowed, borderless or fullscreen", name))?,
+        None => display::DisplayMode::Fullscreen
+    };
+    let (window_width, window_height) = cli::arg_value(&args, "--window-size")
+        .and_then(|size| cli::parse_size(size))
+        .unwrap_or((SCREEN_WIDTH, SCREEN_HEIGHT));
+
+    let mut window = video_subsystem.window("Asteroids",window_width,window_height)
         .position_centered()
+        .resizable()
         .build()
         .expect("Could not initialize video subsystem");
+    display_mode.apply(&mut window)?;
     
     let mut canvas = window.into_canvas().build()
         .expect("Failed ot initialize canvas");
+
+    //Everything is drawn on a SCREEN_WIDTH x SCREEN_HEIGHT playfield that SDL scales to the window,
+    //letterboxing when the aspect ratios differ. `--integer-scale` keeps pixels crisp at the cost of bigger borders
+    canvas.set_logical_size(SCREEN_WIDTH, SCREEN_HEIGHT).map_err(|e| e.to_string())?;
+    canvas.set_integer_scale(args.iter().any(|arg| arg == "--integer-scale"))?;
         
     let texture_creator = canvas.texture_creator();
     let mut texture_manager = texture_manager::TextureManager::new(&texture_creator);
-
-    //Load Images
-    texture_manager.load("img/ship.png")?; //Loads Ship Texture to Memory
-    texture_manager.load("img/asteroid1.png")?; //Loads Asteroid Texture to Memory
-    texture_manager.load("img/missile.png")?; //Loads Missile Texture to Memory
-
+    let mut text_cache = text::TextCache::new(&texture_creator);
 
     //Sound Manager
-    let mut sound_manager = sound_manager::SoundManager::new();
-
-    //Load the soudns to prevent loading during gameplay
-    sound_manager.load_sound(&MUSIC_FILENAME.to_string(), true);
-    sound_manager.load_sound(&THRUSTER_FILENAME.to_string(), true);
-    sound_manager.load_sound(&SHOOT_FILENAME.to_string(), false);
-    sound_manager.load_sound(&RELOAD_FILENAME.to_string(), false);
+    //Pick the audio backend, e.g. `--audio null` on machines without a sound card
+    let mut sound_manager = sound_manager::create_backend(cli::arg_value(&args, "--audio").map(|name| name.as_str()));
 
     //Prepare fonts
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
-    let font_path: &Path = Path::new(&"fonts/Monocraft.ttf");
-    let mut font = ttf_context.load_font(font_path, 128)?;
-    font.set_style(sdl2::ttf::FontStyle::BOLD);
+    let mut font_manager = texture_manager::FontManager::new(&ttf_context);
+
+    //Anything that fails to load is listed once everything has been tried
+    let mut manifest = assets::AssetManifest::load(ASSET_MANIFEST)?;
+    let mut asset_report = assets::AssetReport::default();
+
+    //Missing images are drawn as a checkerboard
+    texture_manager.set_fallback(assets::placeholder_texture(&mut canvas, &texture_creator)?);
+
+    //Load the preload group up front to prevent loading during gameplay.
+    //Holding on to the textures keeps them cached when unused assets are evicted
+    let mut preloaded_textures = HashMap::new();
+    for entry in manifest.group(PRELOAD_GROUP) {
+        match entry.kind {
+            assets::AssetKind::Texture => {
+                if let Some(texture) = asset_report.check(texture_manager.load(entry.path.as_str())) {
+                    preloaded_textures.insert(entry.path.to_string(), texture);
+                }
+            },
+            assets::AssetKind::Sound => { asset_report.check(sound_manager.load_sound(&entry.id, &entry.path)); },
+            assets::AssetKind::Font => {} //Loaded below, the HUD needs its own handle
+        }
+    }
+    let font = asset_report.check(manifest.font(HUD_FONT).and_then(|details| font_manager.load(&details)));
+
+    //A broken layout file falls back to the one built into the game
+    let mut hud = asset_report.check(hud::HudLayout::load(HUD_LAYOUT)).unwrap_or_else(hud::HudLayout::builtin);
+
+    asset_report.print();
+
+    //There is nothing to stand in for the font, so the game can't run without it
+    let mut font = font.ok_or_else(|| format!("The {} font is required to draw the HUD", HUD_FONT))?;
+
+    //Watch the manifest and everything in it so changes show up without a restart
+    let mut watcher = assets::AssetWatcher::new();
+    watcher.watch(ASSET_MANIFEST);
+    watcher.watch(HUD_LAYOUT);
+    for entry in manifest.entries() {
+        watcher.watch(&entry.path);
+    }
     
     let mut event_pump = sdl_context.event_pump()?;
     let mut key_manager: HashMap<String,bool> = HashMap::new();
 
-    let mut gs = State{
-        ecs: World::new()
+    let mut sim = simulation::Simulation::new(&settings);
+    sim.ecs.insert(camera::Camera::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+    let mut audio_events = sim.subscribe();
+    let mut timestep = simulation::FixedTimestep::new(settings.tick_rate);
+
+    //Input recording and playback, see replay.rs
+    let record_path = cli::arg_value(&args, "--record");
+    let mut recording = record_path.map(|_| replay::Replay::new(settings));
+    let mut playback = loaded_replay.map(replay::Playback::new);
+    let mut pending_commands = 0u8;
+
+    let mut options = state::Options{
+        music: true,
+        unlocked_fps: false,
+        display_mode
     };
-    gs.ecs.register::<components::Position>();
-    gs.ecs.register::<components::Renderable>();
-    gs.ecs.register::<components::Player>();
-    gs.ecs.register::<components::Asteroid>();
-    gs.ecs.register::<components::Missile>();
-    gs.ecs.register::<components::GameData>();
-    // gs.ecs.register::<components::Star>();
-    gs.ecs.register::<components::SoundCue>();
-    gs.ecs.insert(DeltaTime(0.0));
-
-    let mut dispatcher = DispatcherBuilder::new() //Creates a dispatcher to run systems
-        .with(asteroid::AsteroidMover, "asteroid_mover", &[])
-        .with(asteroid::AsteroidCollider, "asteroid_collider", &[])
-        .with(missile::MissileMover, "missile_mover", &[])
-        .with(missile::MissileStriker, "missile_striker", &[])
-        .build();
-
-    game::load_world(&mut gs.ecs);
+
+    //A replay plays straight away, otherwise the title screen comes first
+    let mut state = if playback.is_some() { state::AppState::Playing } else { state::AppState::Title };
+    let mut world_fresh = true; //Nothing has been simulated since the world was loaded
+    let mut previous_best = GAMESTATE.lock().unwrap().highscore;
 
     //Start Music Playing
-    sound_manager.resume_sound(&MUSIC_FILENAME.to_string());
+    sound_manager.resume_sound(MUSIC_SOUND);
 
     let mut frame_count = 0u64;
     let mut last_frame_time = Instant::now();
@@ -401,47 +360,27 @@ fn main() -> Result<(),String>{
     //init at 100 to draw initial UI
     // let mut loop_count = 100;
 
-    let mut unlockedFPS = false;
-    let mut musicPlaying = true;
-
     'running: loop {
         for event in event_pump.poll_iter(){
+            //Held keys are tracked in every state so nothing is left stuck down after a menu
             match event {
                 Event::Quit {..} => {
                     break 'running
                 }, 
-                Event::KeyDown {keycode: Some(Keycode::Escape),..} => {
-                    break 'running
-                },
                 Event::KeyDown {keycode: Some(Keycode::Space),..} => {
                     utils::key_down(&mut key_manager, " ".to_string());
                 },
                 Event::KeyUp {keycode: Some(Keycode::Space),..} => {
                     utils::key_up(&mut key_manager, " ".to_string());
                 },
-                Event::KeyUp {keycode: Some(Keycode::P),..} => {
-                    musicPlaying = !musicPlaying;
-                    if musicPlaying {
-                        sound_manager.resume_sound(&MUSIC_FILENAME.to_string())
-                    } else {
-                        sound_manager.stop_sound(&MUSIC_FILENAME.to_string());
-                    }
-                },
-                Event::KeyUp {keycode: Some(Keycode::U),..} => {
-                    println!("FPS Toggle");
-                    unlockedFPS = !unlockedFPS;
-                    if unlockedFPS {
-                        sound_manager.stop_sound(&THRUSTER_FILENAME.to_string());
-                    }
+                Event::KeyUp {keycode:Some(Keycode::I),..} if state.is_playing() => {
+                    pending_commands |= replay::TOGGLE_INVINCIBILITY;
                 },
-                Event::KeyUp {keycode:Some(Keycode::I),..} => {
-                    game::toggle_invincibility(&mut gs.ecs);
+                Event::KeyUp {keycode:Some(Keycode::O),..} if state.is_playing() => {
+                    pending_commands |= replay::CREATE_THOUSAND_ASTEROIDS;
                 },
-                Event::KeyUp {keycode:Some(Keycode::O),..} => {
-                    game::create_thousand_asteroids(&mut gs.ecs);
-                },
-                Event::KeyUp {keycode:Some(Keycode::H),..} => {
-                    game::toggle_show_controls(&mut gs.ecs);
+                Event::KeyUp {keycode:Some(Keycode::H),..} if state.is_playing() => {
+                    game::toggle_show_controls(&mut sim.ecs);
                 },
                 Event::KeyDown {keycode,..} => {
                     match keycode {
@@ -461,43 +400,207 @@ fn main() -> Result<(),String>{
                 },
                 _ => {}
             }
+
+            match state.handle_event(&event) {
+                state::Transition::Stay => {},
+                state::Transition::Quit => break 'running,
+                state::Transition::Toggle(state::Setting::Music) => {
+                    options.music = !options.music;
+                    if options.music {
+                        sound_manager.resume_sound(MUSIC_SOUND)
+                    } else {
+                        sound_manager.stop_sound(MUSIC_SOUND);
+                    }
+                },
+                state::Transition::Toggle(state::Setting::UnlockedFps) => {
+                    println!("FPS Toggle");
+                    options.unlocked_fps = !options.unlocked_fps;
+                    if options.unlocked_fps {
+                        sound_manager.stop_sound(THRUSTER_SOUND);
+                    } else if state.is_playing() && game::is_thrusting(&sim.ecs) {
+                        //No ThrusterStarted comes while the key stays held, so pick the sound back up here
+                        sound_manager.resume_sound(THRUSTER_SOUND);
+                    }
+                },
+                state::Transition::Toggle(state::Setting::Display) => {
+                    options.display_mode = options.display_mode.next();
+                    options.display_mode.apply(canvas.window_mut())?;
+                },
+                state::Transition::To(next) => {
+                    //Leaving play silences the thruster, coming back picks it up again if the ship is still thrusting
+                    if state.is_playing() && !next.is_playing() {
+                        sound_manager.stop_sound(THRUSTER_SOUND);
+                    }
+                    if next.is_playing() && !state.is_playing() {
+                        if game::is_thrusting(&sim.ecs) && !options.unlocked_fps {
+                            sound_manager.resume_sound(THRUSTER_SOUND);
+                        }
+                        //Time spent away from play doesn't count, so the first tick back sees an ordinary frame
+                        timestep.reset();
+                        last_frame_time = Instant::now();
+                    }
+                    //Starting from the title gets a fresh world, resuming from pause carries on with the old one
+                    if let (state::AppState::Title, state::AppState::Playing) = (&state, &next) {
+                        if !world_fresh {
+                            finish_recording(&mut recording, record_path, sim.score())?;
+                            //A replay only covers the game it was recorded from, the new one is played by hand
+                            playback = None;
+                            sim.reset();
+                            //The reset world has none of the resources or readers added from out here
+                            sim.ecs.insert(camera::Camera::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+                            audio_events = sim.subscribe();
+                            world_fresh = true;
+                        }
+                        previous_best = GAMESTATE.lock().unwrap().highscore;
+                    }
+                    state = next;
+                }
+            }
         }
 
         let now = Instant::now();
         let delta_time = now.duration_since(last_frame_time).as_secs_f64();
         last_frame_time = now;
 
-        if delta_time != 0.0 {
-            fps = (1.0/delta_time) as u64;
+        //Average the FPS over a second so the counter text only changes (and gets re-rasterized) once a second
+        frame_count += 1;
+        let fps_elapsed = now.duration_since(last_frame_time_fps).as_secs_f64();
+        if fps_elapsed >= 1.0 {
+            fps = (frame_count as f64 / fps_elapsed) as u64;
+            frame_count = 0;
+            last_frame_time_fps = now;
         }
       
 
-        gs.ecs.write_resource::<DeltaTime>().0 = delta_time;
-
-        game::update(&mut gs.ecs, &mut key_manager,delta_time);
-        dispatcher.dispatch(&mut gs.ecs); //Runs the dispatcher and all systems run events
-        gs.ecs.maintain(); //Removes all entities that have been deleted
-
-        let _ = render(&mut canvas,&mut texture_manager, &texture_creator,&font, &gs.ecs, fps);
-        let cues = gs.ecs.read_storage::<components::SoundCue>();
-        let entities = gs.ecs.entities();
-        for (cue, entitiy) in (&cues, &entities).join() {
-            if(!unlockedFPS) {
-                if cue.sc_type == components::SoundCueType::PlaySound {
-                    sound_manager.play_sound(cue.filename.to_string());
-                } else if cue.sc_type == components::SoundCueType::LoopSound {
-                    sound_manager.resume_sound(&cue.filename.to_string());
-                } else if cue.sc_type == components::SoundCueType::StopSound {
-                    sound_manager.stop_sound(&cue.filename.to_string());
+        //Reload anything that changed on disk
+        for path in watcher.changed() {
+            if path == HUD_LAYOUT {
+                match hud::HudLayout::load(HUD_LAYOUT) {
+                    Ok(reloaded) => {
+                        hud = reloaded;
+                        println!("Reloaded {}", HUD_LAYOUT);
+                    },
+                    Err(e) => println!("{}", e)
                 }
+                continue;
+            }
+            if path == ASSET_MANIFEST {
+                match assets::AssetManifest::load(ASSET_MANIFEST) {
+                    Ok(reloaded) => {
+                        manifest = reloaded;
+                        for entry in manifest.entries() {
+                            watcher.watch(&entry.path);
+                        }
+                        //Sounds can't be loaded mid-game like textures, so pick up any new ones now
+                        for entry in manifest.group(PRELOAD_GROUP) {
+                            if entry.kind == assets::AssetKind::Sound {
+                                sound_manager.load_sound(&entry.id, &entry.path).ok();
+                            }
+                        }
+                        println!("Reloaded {}", ASSET_MANIFEST);
+                    },
+                    Err(e) => println!("{}", e)
+                }
+                continue;
+            }
+
+            for entry in manifest.with_path(&path) {
+                let reloaded = match entry.kind {
+                    assets::AssetKind::Texture => texture_manager.reload(path.as_str()).map(|texture| {
+                        if preloaded_textures.contains_key(&path) {
+                            preloaded_textures.insert(path.to_string(), texture);
+                        }
+                    }),
+                    assets::AssetKind::Sound => sound_manager.reload_sound(&entry.id, &path),
+                    assets::AssetKind::Font if entry.id == HUD_FONT => manifest.font(HUD_FONT)
+                        .and_then(|details| font_manager.reload(&details))
+                        .map(|reloaded| {
+                            font = reloaded;
+                            text_cache.clear();
+                        }),
+                    assets::AssetKind::Font => Ok(())
+                };
+                match reloaded {
+                    Ok(()) => println!("Reloaded {} ({})", entry.id, path),
+                    Err(e) => println!("{}", e)
+                }
+            }
+        }
+
+        //Run as many fixed ticks as the frame time covers so the systems always see the same dt.
+        //Only Playing moves the world on, every other state leaves it where it was
+        if state.is_playing() {
+            timestep.advance(delta_time);
+        }
+        while timestep.tick() {
+            let mut frame = replay::InputFrame::capture(&key_manager, pending_commands);
+            pending_commands = 0;
+
+            //During playback the recorded frame replaces whatever SDL gave us
+            let mut playback_finished = false;
+            if let Some(active) = &mut playback {
+                match active.next_frame() {
+                    Some(recorded) => frame = recorded,
+                    None => {
+                        println!("Replay finished with score {} (recorded {})", sim.score(), active.replay().final_score);
+                        playback_finished = true;
+                    }
+                }
+            }
+            if playback_finished {
+                playback = None;
+            }
+
+            frame.apply_keys(&mut key_manager);
+            if let Some(recording) = &mut recording {
+                recording.record(frame);
+            }
+            sim.apply_commands(&frame);
+            sim.step(&mut key_manager, timestep.dt());
+            world_fresh = false;
+
+            for event in sim.ecs.read_resource::<events::GameEvents>().read(&mut audio_events) {
+                if(!options.unlocked_fps) {
+                    sound_manager.play_event(event);
+                }
+                //Between levels is a quiet moment to drop assets nothing holds on to any more
+                if let events::GameEvent::LevelCleared{..} = event {
+                    texture_manager.evict_unused();
+                    font_manager.evict_unused();
+                    sound_manager.evict_unused();
+                }
+            }
+
+            if game::is_game_over(&sim.ecs) {
+                finish_recording(&mut recording, record_path, sim.score())?;
+                state = state::AppState::game_over(sim.score(), previous_best);
+                //Whatever time was left over belongs to the finished game, not the next state
+                timestep.reset();
+                break;
             }
-            entities.delete(entitiy).ok();
         }
-        if(!unlockedFPS){
+
+        let _ = render(&mut canvas,&mut texture_manager, &mut text_cache, &hud, &font, &manifest, &sim.ecs, fps, timestep.alpha());
+        let _ = state.render(&mut canvas, &font, &mut text_cache, &hud, &options);
+        canvas.present();
+        if(!options.unlocked_fps){
             std::thread::sleep(Duration::new(0,1_000_000_000u32/60));
         }
     }
 
+    //Quitting mid-game still keeps what was recorded so far
+    finish_recording(&mut recording, record_path, sim.score())?;
+
+    Ok(())
+}
+
+// Saves the recording when its game ends, a recording only ever covers one game
+fn finish_recording(recording: &mut Option<replay::Replay>, path: Option<&String>, score: u32) -> Result<(), String> {
+    if let (Some(mut finished), Some(path)) = (recording.take(), path) {
+        finished.final_score = score;
+        finished.save(Path::new(path))?;
+        println!("Saved replay to {}", path);
+    }
     Ok(())
 }
 
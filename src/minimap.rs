@@ -0,0 +1,61 @@
+use specs::{World, WorldExt, Join};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+
+use crate::camera::Camera;
+use crate::collision;
+use crate::components::{self, EdgePolicy};
+use crate::physics::Playfield;
+
+// How much of the playfield the minimap covers, in screen widths around the player
+const RANGE_IN_VIEWS: f64 = 3.0;
+const MIN_DOT_SIZE: u32 = 2;
+
+// Radar of the asteroids around the player, with the part of the playfield on screen outlined
+pub fn draw(canvas: &mut WindowCanvas, area: Rect, ecs: &World, camera: &Camera, playfield: &Playfield, player_at: (f64, f64)) -> Result<(), String> {
+    let scale = area.width() as f64 / (camera.view_width as f64 * RANGE_IN_VIEWS);
+    let centre = area.center();
+
+    //Offset from the player, taking the short way round for anything that wraps
+    let offset = |x: f64, y: f64, wraps: bool| collision::offset_between(player_at, (x, y), wraps, playfield);
+    let to_map = |(dx, dy): (f64, f64)| {
+        (centre.x() + (dx * scale) as i32, centre.y() + (dy * scale) as i32)
+    };
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(area)?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    let positions = ecs.read_storage::<components::Position>();
+    let asteroids = ecs.read_storage::<components::Asteroid>();
+    let renderables = ecs.read_storage::<components::Renderable>();
+    let asteroid_wraps = playfield.edges.asteroid == EdgePolicy::Wrap;
+    canvas.set_draw_color(Color::RGB(200, 200, 200));
+    for (pos, renderable, _) in (&positions, &renderables, &asteroids).join() {
+        let (x, y) = to_map(offset(pos.x, pos.y, asteroid_wraps));
+        let size = ((renderable.o_w as f64 * scale) as u32).max(MIN_DOT_SIZE);
+        let dot = Rect::from_center((x, y), size, size);
+        if area.contains_rect(dot) {
+            canvas.fill_rect(dot)?;
+        }
+    }
+
+    //The view follows the player, so only its offset from them matters
+    let (view_x, view_y) = to_map((camera.x - player_at.0, camera.y - player_at.1));
+    let view = Rect::from_center(
+        (view_x, view_y),
+        ((camera.view_width as f64 * scale) as u32).max(1),
+        ((camera.view_height as f64 * scale) as u32).max(1)
+    );
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.draw_rect(view)?;
+
+    canvas.set_draw_color(Color::RGB(255, 0, 0));
+    canvas.fill_rect(Rect::from_center(centre, MIN_DOT_SIZE * 2, MIN_DOT_SIZE * 2))?;
+
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.draw_rect(area)
+}
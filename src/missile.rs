@@ -1,46 +1,11 @@
 use specs::prelude::*;
-use specs::{World,WorldExt,Entities,Join};
+use specs::{Entities,Entity};
 
-use crate::sound_manager;
+use crate::{components, collision, physics};
+use crate::events::{GameEvent, GameEvents};
 
-use crate::{components};
-
-pub struct MissileMover;
-
-impl<'a> System<'a> for MissileMover {
-    type SystemData = (
-        WriteStorage<'a, components::Position>,
-        WriteStorage<'a, components::Renderable>,
-        WriteStorage<'a, components::Missile>,
-        Entities<'a>,
-        Read<'a,crate::DeltaTime>
-    );
-
-    fn run(&mut self, mut data: Self::SystemData) {
-        let (mut positions, mut renderables, missiles, entities, deltatime) = data;
-        for(pos, rend, missile, entity) in (&mut positions, &mut renderables, &missiles, &entities).join(){
-            let radians = pos.rot.to_radians();
-
-            let move_x = missile.speed * radians.sin() * deltatime.0;
-            let move_y = missile.speed * radians.cos() * deltatime.0;
-            pos.x += move_x;
-            pos.y -= move_y;
-
-            pos.section = (pos.x as u32/ crate::SECTION_WIDTH) * crate::NO_OF_SECTIONS +  (pos.y as u32 / crate::SECTION_HEIGHT);
-
-            if pos.x > crate::SCREEN_WIDTH.into() || pos.x < 0.0 || pos.y > crate::SCREEN_HEIGHT.into() || pos.y < 0.0 {
-                entities.delete(entity).unwrap();
-
-                let mut sound_manager = sound_manager::SoundManager::new();
-                sound_manager.load_sound(&crate::RELOAD_FILENAME.to_string(), false);
-                sound_manager.play_sound(crate::RELOAD_FILENAME.to_string());
-               
-            }
-
-            rend.rot = pos.rot;
-        }
-    }
-}
+const SPLIT_ANGLE: f64 = 45.0; //Degrees either side of the parent's path
+const SPLIT_SPEED_UP: f64 = 1.25;
 
 pub struct MissileStriker;
 
@@ -50,63 +15,78 @@ impl<'a> System<'a> for MissileStriker {
         WriteStorage<'a, components::Renderable>,
         WriteStorage<'a, components::Missile>,
         WriteStorage<'a, components::Asteroid>,
-        WriteStorage<'a, components::GameData>,
-        Entities<'a>
+        Write<'a, GameEvents>,
+        WriteStorage<'a, components::Collider>,
+        Entities<'a>,
+        Read<'a, collision::Contacts>,
+        WriteStorage<'a, components::Velocity>,
+        WriteStorage<'a, components::AngularVelocity>,
+        WriteStorage<'a, components::ScreenEdge>,
+        Read<'a, physics::Playfield>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (positions, renderables, missiles, asteroids,_, entities) = &data;
+        let (positions, renderables, missiles, asteroids,_,_, entities, contacts, velocities, angular_velocities,_,_) = &data;
         let mut asteroid_creation = Vec::<components::PendingAsteroid>::new();
-        let mut score:u32 = 0;
-
-        for( asteroid_pos,asteroid_rend,_, asteroid_entity) in (positions,renderables,asteroids,entities).join(){
-            for(missile_pos,_,_,missile_entity) in (positions,renderables,missiles,entities).join(){
-                if asteroid_pos.section != missile_pos.section {
-                    continue;
-                }
-
-                let diff_x = (asteroid_pos.x - missile_pos.x).abs();
-                let diff_y = (asteroid_pos.y - missile_pos.y).abs();
-                let dist = (diff_x * diff_x + diff_y * diff_y);
+        let mut destroyed_events = Vec::<GameEvent>::new();
 
-                if dist < (asteroid_rend.o_w as f64  / 2.0)*(asteroid_rend.o_w as f64  / 2.0){
-                    score += 10;
-                    entities.delete(missile_entity).ok();
-                    entities.delete(asteroid_entity).ok();
+        let mut destroyed = Vec::<Entity>::new();
+        let mut spent = Vec::<Entity>::new();
 
-                    let mut sound_manager = sound_manager::SoundManager::new();
-                    sound_manager.load_sound(&crate::RELOAD_FILENAME.to_string(), false);
-                    sound_manager.play_sound(crate::RELOAD_FILENAME.to_string());
-
-                    let new_size = asteroid_rend.o_w / 2;
-                    if new_size >= 25 {
-                        asteroid_creation.push(components::PendingAsteroid{
-                            x: asteroid_pos.x,
-                            y: asteroid_pos.y,
-                            rot: asteroid_pos.rot - 90.0,
-                            section: asteroid_pos.section,
-                            size: new_size
-                        });
-                        asteroid_creation.push(components::PendingAsteroid{
-                            x: asteroid_pos.x,
-                            y: asteroid_pos.y,
-                            rot: asteroid_pos.rot + 90.0,
-                            section: asteroid_pos.section,
-                            size: new_size
-                        });
-                    }
+        for contact in contacts.0.iter() {
+            let (missile_entity, asteroid_entity) = (contact.a, contact.b);
+            if missiles.get(missile_entity).is_none() || asteroids.get(asteroid_entity).is_none() {
+                continue;
+            }
+            //A missile only takes out one asteroid and an asteroid only splits once
+            if spent.contains(&missile_entity) || destroyed.contains(&asteroid_entity) {
+                continue;
+            }
+            let (asteroid_pos, asteroid_rend, asteroid_vel) = match (positions.get(asteroid_entity), renderables.get(asteroid_entity), velocities.get(asteroid_entity)) {
+                (Some(pos), Some(rend), Some(vel)) => (pos, rend, vel),
+                _ => continue
+            };
+            let asteroid_spin = angular_velocities.get(asteroid_entity).map_or(0.0, |spin| spin.0);
+
+            entities.delete(missile_entity).ok();
+            entities.delete(asteroid_entity).ok();
+            spent.push(missile_entity);
+            destroyed.push(asteroid_entity);
+            destroyed_events.push(GameEvent::AsteroidDestroyed{
+                x: asteroid_pos.x,
+                y: asteroid_pos.y,
+                size: asteroid_rend.o_w
+            });
+
+            //The halves fly apart either side of the original path, a little faster and spinning opposite ways
+            let new_size = asteroid_rend.o_w / 2;
+            if new_size >= 25 {
+                for side in [-1.0, 1.0] {
+                    let vel = physics::rotate_velocity(asteroid_vel, side * SPLIT_ANGLE);
+                    asteroid_creation.push(components::PendingAsteroid{
+                        x: asteroid_pos.x,
+                        y: asteroid_pos.y,
+                        rot: asteroid_pos.rot,
+                        vel_x: vel.x * SPLIT_SPEED_UP,
+                        vel_y: vel.y * SPLIT_SPEED_UP,
+                        rot_speed: asteroid_spin * side,
+                        size: new_size
+                    });
                 }
             }
         }
 
-        let (mut positions, mut renderables, _, mut asteroids,_,entities) = data;
+        let (mut positions, mut renderables, _, mut asteroids,mut events,mut colliders,entities,_,mut velocities,mut angular_velocities,mut screen_edges,playfield) = data;
         // let (ref positions, ref renderables, _, ref asteroids,ref entities) = data;
         for new_asteroid in asteroid_creation {
             let new_ast = entities.create();
-            positions.insert(new_ast, components::Position{x:new_asteroid.x, y:new_asteroid.y, rot:new_asteroid.rot,section: new_asteroid.section}).ok();
-            asteroids.insert(new_ast, components::Asteroid{speed: 150.0, rot_speed: 150.0}).ok();
+            positions.insert(new_ast, components::Position{x:new_asteroid.x, y:new_asteroid.y, rot:new_asteroid.rot}).ok();
+            asteroids.insert(new_ast, components::Asteroid).ok();
+            velocities.insert(new_ast, components::Velocity{x: new_asteroid.vel_x, y: new_asteroid.vel_y}).ok();
+            angular_velocities.insert(new_ast, components::AngularVelocity(new_asteroid.rot_speed)).ok();
+            screen_edges.insert(new_ast, components::ScreenEdge(playfield.edges.asteroid)).ok();
             renderables.insert(new_ast, components::Renderable{
-                tex_name: "img/asteroid1.png".to_string(),
+                texture: "asteroid".to_string(),
                 i_w: 100,
                 i_h: 100,
                 o_w: new_asteroid.size,
@@ -115,15 +95,12 @@ impl<'a> System<'a> for MissileStriker {
                 total_frames: 1,
                 rot: 0.0
             }).ok();
+            colliders.insert(new_ast, components::Collider{
+                radius: new_asteroid.size as f64 / 2.0,
+                layer: components::CollisionLayer::Asteroid
+            }).ok();
         }
 
-        let(_,_,_,_,mut game_data,_) = data;
-        for mut gamedata in (&mut game_data).join(){
-            gamedata.score += score;
-            let mut gamestate = crate::GAMESTATE.lock().unwrap();
-            if gamedata.score > gamestate.highscore {
-                gamestate.highscore = gamedata.score;
-            }
-        }
+        events.iter_write(destroyed_events);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,194 @@
+use specs::{System, WriteStorage, ReadStorage, Join, Read, Write};
+use specs::prelude::Entities;
+
+use crate::components::{self, EdgePolicy};
+use crate::events::{GameEvent, GameEvents};
+
+// Moves and spins everything with a Velocity or AngularVelocity.
+// Heading (Position.rot) only changes through AngularVelocity or player input,
+// so how a sprite is facing is independent of where it is going.
+pub struct Integrator;
+
+impl<'a> System<'a> for Integrator {
+    type SystemData = (
+        WriteStorage<'a, components::Position>,
+        WriteStorage<'a, components::Renderable>,
+        ReadStorage<'a, components::Velocity>,
+        ReadStorage<'a, components::AngularVelocity>,
+        Read<'a, crate::DeltaTime>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut positions, mut renderables, velocities, angular_velocities, deltatime) = data;
+        let deltatime = deltatime.0;
+
+        for (pos, vel) in (&mut positions, &velocities).join() {
+            pos.x += vel.x * deltatime;
+            pos.y += vel.y * deltatime;
+        }
+
+        for (pos, angular_vel) in (&mut positions, &angular_velocities).join() {
+            pos.rot += angular_vel.0 * deltatime;
+            if pos.rot > 360.0 {
+                pos.rot -= 360.0;
+            }
+            if pos.rot < 0.0 {
+                pos.rot += 360.0;
+            }
+        }
+
+        //Update the graphic to reflect the rotation
+        for (pos, rend) in (&positions, &mut renderables).join() {
+            rend.rot = pos.rot;
+        }
+    }
+}
+
+// Velocity for travelling at speed along a heading, 0 degrees being straight up the screen
+pub fn velocity_from_heading(rot: f64, speed: f64) -> components::Velocity {
+    let radians = rot.to_radians();
+    components::Velocity{
+        x: speed * radians.sin(),
+        y: -speed * radians.cos()
+    }
+}
+
+// The same velocity turned by the given number of degrees
+pub fn rotate_velocity(vel: &components::Velocity, degrees: f64) -> components::Velocity {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    components::Velocity{
+        x: vel.x * cos - vel.y * sin,
+        y: vel.x * sin + vel.y * cos
+    }
+}
+
+// Edge policy given to each kind of entity when it is created
+#[derive(Clone, Copy)]
+pub struct EdgePolicies {
+    pub player: EdgePolicy,
+    pub asteroid: EdgePolicy,
+    pub missile: EdgePolicy
+}
+
+impl Default for EdgePolicies {
+    fn default() -> Self {
+        Self {
+            player: EdgePolicy::Wrap,
+            asteroid: EdgePolicy::Wrap,
+            missile: EdgePolicy::Wrap
+        }
+    }
+}
+
+// The arena everything lives in, read by the systems instead of the screen constants
+// so tests can use tiny arenas and other modes huge ones
+#[derive(Clone, Copy)]
+pub struct Playfield {
+    pub width: u32,
+    pub height: u32,
+    pub grid_cell_size: u32, //See collision::SpatialGrid
+    pub edges: EdgePolicies
+}
+
+// Smallest playfield, in either direction, that still leaves room to spawn asteroids away from the player
+pub const MIN_PLAYFIELD_SIZE: u32 = 200;
+
+impl Playfield {
+    pub fn centre(&self) -> (f64, f64) {
+        (self.width as f64 / 2.0, self.height as f64 / 2.0)
+    }
+
+    // Gap kept between new asteroids and the edges, 50 on a full screen and less on small playfields
+    pub fn spawn_margin(&self) -> f64 {
+        (self.width.min(self.height) as f64 / 10.0).min(50.0)
+    }
+
+    // How close to the player a new asteroid may appear, 150 on a full screen and less on small playfields
+    pub fn spawn_clearance(&self) -> f64 {
+        (self.width.min(self.height) as f64 / 5.0).min(150.0)
+    }
+}
+
+impl Default for Playfield {
+    fn default() -> Self {
+        Self {
+            width: crate::SCREEN_WIDTH,
+            height: crate::SCREEN_HEIGHT,
+            grid_cell_size: crate::GRID_CELL_SIZE,
+            edges: EdgePolicies::default()
+        }
+    }
+}
+
+// Applies each entity's EdgePolicy once it has been moved
+pub struct ScreenWrap;
+
+impl<'a> System<'a> for ScreenWrap {
+    type SystemData = (
+        WriteStorage<'a, components::Position>,
+        WriteStorage<'a, components::Velocity>,
+        ReadStorage<'a, components::Renderable>,
+        ReadStorage<'a, components::ScreenEdge>,
+        ReadStorage<'a, components::Missile>,
+        WriteStorage<'a, components::Despawned>,
+        Write<'a, GameEvents>,
+        Entities<'a>,
+        Read<'a, Playfield>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut positions, mut velocities, renderables, edges, missiles, mut despawned_marks, mut events, entities, playfield) = data;
+        let width = playfield.width as f64;
+        let height = playfield.height as f64;
+
+        let mut despawned = Vec::new();
+        for (pos, edge, entity) in (&mut positions, &edges, &entities).join() {
+            match edge.0 {
+                EdgePolicy::Wrap => {
+                    if pos.x > width {
+                        pos.x -= width;
+                    }
+                    else if pos.x < 0.0 {
+                        pos.x += width;
+                    }
+
+                    if pos.y > height {
+                        pos.y -= height;
+                    }
+                    else if pos.y < 0.0 {
+                        pos.y += height;
+                    }
+                },
+                EdgePolicy::Bounce => {
+                    let (half_width, half_height) = match renderables.get(entity) {
+                        Some(rend) => ((rend.o_w / 2) as f64, (rend.o_h / 2) as f64),
+                        None => (0.0, 0.0)
+                    };
+                    //Only flip when heading further out so nothing gets stuck flipping back and forth
+                    if let Some(vel) = velocities.get_mut(entity) {
+                        if (pos.x > width - half_width && vel.x > 0.0) || (pos.x < half_width && vel.x < 0.0) {
+                            vel.x = -vel.x;
+                        }
+                        if (pos.y > height - half_height && vel.y > 0.0) || (pos.y < half_height && vel.y < 0.0) {
+                            vel.y = -vel.y;
+                        }
+                    }
+                },
+                EdgePolicy::Despawn => {
+                    if pos.x > width || pos.x < 0.0 || pos.y > height || pos.y < 0.0 {
+                        entities.delete(entity).ok();
+                        despawned_marks.insert(entity, components::Despawned).ok();
+                        despawned.push(entity);
+                    }
+                }
+            }
+        }
+
+        //A missile leaving the screen frees up a shot
+        for entity in despawned {
+            if missiles.get(entity).is_some() {
+                events.single_write(GameEvent::MissileExpired);
+            }
+        }
+    }
+}
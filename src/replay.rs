@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::utils;
+use crate::simulation::SimulationSettings;
+use crate::physics::{EdgePolicies, Playfield, MIN_PLAYFIELD_SIZE};
+use crate::components::EdgePolicy;
+
+// Keys game::update reads from the key manager, each stored as one bit of an InputFrame
+const RECORDED_KEYS: [(&str, u8); 4] = [
+    ("W", 1 << 0),
+    ("A", 1 << 1),
+    ("D", 1 << 2),
+    (" ", 1 << 3)
+];
+
+// One-off commands that change the simulation outside of game::update
+pub const TOGGLE_INVINCIBILITY: u8 = 1 << 4;
+pub const CREATE_THOUSAND_ASTEROIDS: u8 = 1 << 5;
+
+const MAGIC: &[u8; 4] = b"AREP";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 4 + 1 + 8 + 4 + 1 + 3 + 12 + 4;
+
+// Bits of the header's settings byte
+const ASTEROID_COLLISIONS: u8 = 1 << 0;
+
+// Everything the simulation was fed during a single tick
+#[derive(Clone, Copy, PartialEq)]
+pub struct InputFrame(pub u8);
+
+impl InputFrame {
+    // Packs the current key state and any pending commands into a frame
+    pub fn capture(key_manager: &HashMap<String,bool>, commands: u8) -> Self {
+        let mut bits = commands;
+        for (key, bit) in RECORDED_KEYS {
+            if utils::is_key_pressed(key_manager, key) {
+                bits |= bit;
+            }
+        }
+        InputFrame(bits)
+    }
+
+    // Writes the recorded keys back into a key manager for game::update to read
+    pub fn apply_keys(&self, key_manager: &mut HashMap<String,bool>) {
+        for (key, bit) in RECORDED_KEYS {
+            if self.0 & bit != 0 {
+                utils::key_down(key_manager, key.to_string());
+            } else {
+                utils::key_up(key_manager, key.to_string());
+            }
+        }
+    }
+
+    pub fn has_command(&self, command: u8) -> bool {
+        self.0 & command != 0
+    }
+}
+
+// The settings plus the input for every tick, which is all it takes to play a game back
+pub struct Replay {
+    pub settings: SimulationSettings,
+    pub final_score: u32,
+    pub frames: Vec<InputFrame>
+}
+
+impl Replay {
+    pub fn new(settings: SimulationSettings) -> Self {
+        Self {
+            settings,
+            final_score: 0,
+            frames: Vec::new()
+        }
+    }
+
+    pub fn record(&mut self, frame: InputFrame) {
+        self.frames.push(frame);
+    }
+
+    // Header followed by run length encoded frames, input rarely changes between ticks
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.settings.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.settings.tick_rate.to_le_bytes());
+        bytes.push(if self.settings.asteroid_collisions { ASTEROID_COLLISIONS } else { 0 });
+        let playfield = &self.settings.playfield;
+        bytes.push(edge_to_byte(playfield.edges.player));
+        bytes.push(edge_to_byte(playfield.edges.asteroid));
+        bytes.push(edge_to_byte(playfield.edges.missile));
+        bytes.extend_from_slice(&playfield.width.to_le_bytes());
+        bytes.extend_from_slice(&playfield.height.to_le_bytes());
+        bytes.extend_from_slice(&playfield.grid_cell_size.to_le_bytes());
+        bytes.extend_from_slice(&self.final_score.to_le_bytes());
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(frame) = frames.next() {
+            let mut run: u16 = 1;
+            while run < u16::MAX && frames.peek() == Some(&frame) {
+                frames.next();
+                run += 1;
+            }
+            bytes.extend_from_slice(&run.to_le_bytes());
+            bytes.push(frame.0);
+        }
+
+        fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err(format!("{} is not a replay file", path.display()));
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("Unsupported replay version {}", bytes[4]));
+        }
+
+        let mut replay = Replay::new(SimulationSettings{
+            seed: u64::from_le_bytes(bytes[5..13].try_into().unwrap()),
+            tick_rate: u32::from_le_bytes(bytes[13..17].try_into().unwrap()),
+            asteroid_collisions: bytes[17] & ASTEROID_COLLISIONS != 0,
+            playfield: Playfield{
+                width: u32::from_le_bytes(bytes[21..25].try_into().unwrap()),
+                height: u32::from_le_bytes(bytes[25..29].try_into().unwrap()),
+                grid_cell_size: u32::from_le_bytes(bytes[29..33].try_into().unwrap()),
+                edges: EdgePolicies{
+                    player: edge_from_byte(bytes[18])?,
+                    asteroid: edge_from_byte(bytes[19])?,
+                    missile: edge_from_byte(bytes[20])?
+                }
+            }
+        });
+        let playfield = &replay.settings.playfield;
+        if playfield.width.min(playfield.height) < MIN_PLAYFIELD_SIZE || playfield.grid_cell_size == 0 {
+            return Err(format!("{} has an unusable playfield", path.display()));
+        }
+        replay.final_score = u32::from_le_bytes(bytes[33..37].try_into().unwrap());
+
+        let runs = &bytes[HEADER_SIZE..];
+        if runs.len() % 3 != 0 {
+            return Err(format!("{} is truncated", path.display()));
+        }
+        for run in runs.chunks(3) {
+            let count = u16::from_le_bytes([run[0], run[1]]);
+            for _ in 0..count {
+                replay.frames.push(InputFrame(run[2]));
+            }
+        }
+
+        Ok(replay)
+    }
+}
+
+fn edge_to_byte(edge: EdgePolicy) -> u8 {
+    match edge {
+        EdgePolicy::Wrap => 0,
+        EdgePolicy::Bounce => 1,
+        EdgePolicy::Despawn => 2
+    }
+}
+
+fn edge_from_byte(byte: u8) -> Result<EdgePolicy, String> {
+    match byte {
+        0 => Ok(EdgePolicy::Wrap),
+        1 => Ok(EdgePolicy::Bounce),
+        2 => Ok(EdgePolicy::Despawn),
+        _ => Err(format!("Unknown edge policy {} in replay", byte))
+    }
+}
+
+// Feeds a loaded replay back one tick at a time
+pub struct Playback {
+    replay: Replay,
+    next_frame: usize
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_frame: 0
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn next_frame(&mut self) -> Option<InputFrame> {
+        let frame = self.replay.frames.get(self.next_frame).copied();
+        self.next_frame += 1;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::EdgePolicy;
+    use crate::simulation::{self, FixedTimestep, Simulation};
+
+    fn test_settings() -> SimulationSettings {
+        SimulationSettings {
+            seed: 1,
+            tick_rate: 60,
+            asteroid_collisions: true,
+            playfield: Playfield {
+                width: 1280,
+                height: 720,
+                grid_cell_size: 64,
+                edges: EdgePolicies {
+                    player: EdgePolicy::Wrap,
+                    asteroid: EdgePolicy::Bounce,
+                    missile: EdgePolicy::Despawn
+                }
+            }
+        }
+    }
+
+    // Plays the given frames the way the game loop does and records them with the score they end on
+    fn record(settings: SimulationSettings, frames: &[InputFrame]) -> Replay {
+        let dt = FixedTimestep::new(settings.tick_rate).dt();
+        let mut sim = Simulation::new(&settings);
+        let mut key_manager = HashMap::new();
+        let mut replay = Replay::new(settings);
+        for frame in frames {
+            frame.apply_keys(&mut key_manager);
+            replay.record(*frame);
+            sim.apply_commands(frame);
+            sim.step(&mut key_manager, dt);
+        }
+        replay.final_score = sim.score();
+        replay
+    }
+
+    // Spins and fires, with some thrust and a one-off command so every kind of bit shows up
+    fn bot_frames(count: usize) -> Vec<InputFrame> {
+        (0..count).map(|tick| {
+            let mut bits = (1 << 2) | (1 << 3); //D and space
+            if tick % 120 < 30 {
+                bits |= 1 << 0; //W
+            }
+            if tick == 10 {
+                bits |= TOGGLE_INVINCIBILITY;
+            }
+            InputFrame(bits)
+        }).collect()
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let replay = record(test_settings(), &bot_frames(300));
+        let path = std::env::temp_dir().join(format!("asteroids_round_trip_{}.rep", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).ok();
+        let loaded = loaded.unwrap();
+
+        let (saved, read) = (&replay.settings, &loaded.settings);
+        assert_eq!(read.seed, saved.seed);
+        assert_eq!(read.tick_rate, saved.tick_rate);
+        assert_eq!(read.asteroid_collisions, saved.asteroid_collisions);
+        assert_eq!(read.playfield.width, saved.playfield.width);
+        assert_eq!(read.playfield.height, saved.playfield.height);
+        assert_eq!(read.playfield.grid_cell_size, saved.playfield.grid_cell_size);
+        assert!(read.playfield.edges.player == saved.playfield.edges.player);
+        assert!(read.playfield.edges.asteroid == saved.playfield.edges.asteroid);
+        assert!(read.playfield.edges.missile == saved.playfield.edges.missile);
+        assert_eq!(loaded.final_score, replay.final_score);
+        assert!(loaded.frames == replay.frames);
+        assert_eq!(simulation::run_replay(loaded), replay.final_score);
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("asteroids_not_a_replay_{}.rep", std::process::id()));
+        fs::write(&path, b"not a replay at all, just some bytes").unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).ok();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_score() {
+        let mut settings = test_settings();
+        settings.playfield = Playfield::default();
+        let replay = record(settings, &bot_frames(1800));
+        assert!(replay.final_score > 0, "the bot should hit something in 30 seconds");
+
+        let recorded_score = replay.final_score;
+        assert_eq!(simulation::run_replay(replay), recorded_score);
+    }
+}
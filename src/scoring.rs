@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use specs::{System, WriteStorage, Read, Join};
+use specs::shrev::ReaderId;
+
+use crate::components;
+use crate::events::{GameEvent, GameEvents};
+
+const ASTEROID_POINTS: u32 = 10;
+
+pub struct GameState{
+    pub highscore: u32,
+    pub highscore_name: String //Initials entered for the highscore, empty until someone beats it
+}
+
+pub static GAMESTATE: Lazy<Mutex<GameState>> = Lazy::new(|| {
+    Mutex::new(GameState{
+        highscore: 0,
+        highscore_name: String::new()
+    })
+});
+
+// Adds up the score from the event channel and keeps the highscore in step
+pub struct Scorekeeper {
+    reader: ReaderId<GameEvent>
+}
+
+impl Scorekeeper {
+    pub fn new(reader: ReaderId<GameEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'a> System<'a> for Scorekeeper {
+    type SystemData = (
+        WriteStorage<'a, components::GameData>,
+        Read<'a, GameEvents>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut game_data, events) = data;
+        let mut score: u32 = 0;
+        for event in events.read(&mut self.reader) {
+            if let GameEvent::AsteroidDestroyed { .. } = event {
+                score += ASTEROID_POINTS;
+            }
+        }
+        if score == 0 {
+            return;
+        }
+
+        for gamedata in (&mut game_data).join() {
+            gamedata.score += score;
+            let mut gamestate = GAMESTATE.lock().unwrap();
+            if gamedata.score > gamestate.highscore {
+                gamestate.highscore = gamedata.score;
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::time::Instant;
+use specs::{World, WorldExt, Dispatcher, DispatcherBuilder, Join};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::{components, game, asteroid, missile, replay, collision, physics, lifetime, events, scoring, animation};
+
+// Everything that decides how a run plays out, replays store these so they can reproduce it
+#[derive(Clone, Copy)]
+pub struct SimulationSettings {
+    pub seed: u64,
+    pub tick_rate: u32,
+    pub asteroid_collisions: bool, //Asteroids bounce off each other instead of passing through
+    pub playfield: physics::Playfield
+}
+
+// Owns the game World and the systems that run on it.
+// Nothing in here touches SDL or kira so it can be stepped without a window or audio device.
+pub struct Simulation {
+    pub ecs: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    settings: SimulationSettings,
+    games_played: u32
+}
+
+impl Simulation {
+    pub fn new(settings: &SimulationSettings) -> Self {
+        let (ecs, dispatcher) = build_world(settings);
+        Self {
+            ecs,
+            dispatcher,
+            settings: *settings,
+            games_played: 0
+        }
+    }
+
+    // Advances the game by dt seconds using the given key state
+    pub fn step(&mut self, input: &mut HashMap<String,bool>, dt: f64) {
+        self.store_previous_positions();
+        self.ecs.write_resource::<crate::DeltaTime>().0 = dt;
+
+        game::update(&mut self.ecs, input, dt);
+        self.dispatcher.dispatch(&self.ecs); //Runs the dispatcher and all systems run events
+        self.ecs.maintain(); //Removes all entities that have been deleted
+    }
+
+    // Runs the one-off commands recorded in a frame, before the tick they were issued on
+    pub fn apply_commands(&mut self, frame: &replay::InputFrame) {
+        if frame.has_command(replay::TOGGLE_INVINCIBILITY) {
+            game::toggle_invincibility(&mut self.ecs);
+        }
+        if frame.has_command(replay::CREATE_THOUSAND_ASTEROIDS) {
+            game::create_thousand_asteroids(&mut self.ecs);
+        }
+    }
+
+    // Remembers where everything was before the tick so render can blend between the two
+    fn store_previous_positions(&mut self) {
+        let entities = self.ecs.entities();
+        let positions = self.ecs.read_storage::<components::Position>();
+        let mut previous_positions = self.ecs.write_storage::<components::PreviousPosition>();
+        for (entity, pos) in (&entities, &positions).join() {
+            previous_positions.insert(entity, components::PreviousPosition{x: pos.x, y: pos.y, rot: pos.rot}).ok();
+        }
+    }
+
+    // Hands out a reader for the event channel, events are only kept while some reader still has to see them
+    pub fn subscribe(&mut self) -> specs::shrev::ReaderId<events::GameEvent> {
+        self.ecs.write_resource::<events::GameEvents>().register_reader()
+    }
+
+    // Throws away the current game and starts a fresh one on a brand new World, exactly as new() would.
+    // Each game gets its own seed, kept in Seed for the HUD, so passing it to --seed plays that game again.
+    // Resources and event readers added from outside are gone afterwards and have to be added again
+    pub fn reset(&mut self) {
+        self.settings.seed = self.ecs.write_resource::<crate::GameRng>().0.gen::<u64>();
+        (self.ecs, self.dispatcher) = build_world(&self.settings);
+    }
+
+    // Number of games that have ended since the simulation was created
+    pub fn games_played(&self) -> u32 {
+        self.games_played
+    }
+
+    pub fn score(&self) -> u32 {
+        game::get_score(&self.ecs)
+    }
+}
+
+// Sets up the World and the systems for one game
+fn build_world(settings: &SimulationSettings) -> (World, Dispatcher<'static, 'static>) {
+    let mut ecs = World::new();
+    ecs.register::<components::Position>();
+    ecs.register::<components::PreviousPosition>();
+    ecs.register::<components::Velocity>();
+    ecs.register::<components::AngularVelocity>();
+    ecs.register::<components::ScreenEdge>();
+    ecs.register::<components::Lifetime>();
+    ecs.register::<components::Despawned>();
+    ecs.register::<components::Renderable>();
+    ecs.register::<components::Animation>();
+    ecs.register::<components::Player>();
+    ecs.register::<components::Asteroid>();
+    ecs.register::<components::Missile>();
+    ecs.register::<components::Collider>();
+    ecs.register::<components::GameData>();
+    ecs.insert(crate::DeltaTime(0.0));
+    ecs.insert(crate::GameRng(StdRng::seed_from_u64(settings.seed)));
+    ecs.insert(crate::Seed(settings.seed));
+    ecs.insert(collision::SpatialGrid::new(settings.playfield.width, settings.playfield.height, settings.playfield.grid_cell_size));
+    ecs.insert(collision::Contacts::default());
+    ecs.insert(collision::CollisionRules{asteroid_collisions: settings.asteroid_collisions});
+    ecs.insert(settings.playfield);
+    ecs.insert(events::GameEvents::new());
+    let score_reader = ecs.write_resource::<events::GameEvents>().register_reader();
+    let explosion_reader = ecs.write_resource::<events::GameEvents>().register_reader();
+
+    let dispatcher = DispatcherBuilder::new() //Creates a dispatcher to run systems
+        .with(physics::Integrator, "integrator", &[])
+        .with(physics::ScreenWrap, "screen_wrap", &["integrator"])
+        .with(lifetime::LifetimeTicker, "lifetime_ticker", &["integrator"])
+        .with(collision::Broadphase, "broadphase", &["screen_wrap", "lifetime_ticker"])
+        .with(asteroid::AsteroidCollider, "asteroid_collider", &["broadphase"])
+        .with(missile::MissileStriker, "missile_striker", &["broadphase"])
+        .with(asteroid::AsteroidBouncer, "asteroid_bouncer", &["broadphase"])
+        .with(scoring::Scorekeeper::new(score_reader), "scorekeeper", &["missile_striker"])
+        .with(animation::ExplosionSpawner::new(explosion_reader), "explosion_spawner", &["missile_striker"])
+        .with(animation::Animator, "animator", &["explosion_spawner"])
+        .build();
+
+    game::load_world(&mut ecs);
+
+    (ecs, dispatcher)
+}
+
+// Turns variable frame times into a whole number of fixed length ticks
+pub struct FixedTimestep {
+    dt: f64,
+    accumulator: f64
+}
+
+// Longest frame we will try to catch up on, anything slower just runs the game in slow motion
+const MAX_FRAME_TIME: f64 = 0.25;
+
+impl FixedTimestep {
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            dt: 1.0 / tick_rate.max(1) as f64,
+            accumulator: 0.0
+        }
+    }
+
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    // Drops any time still waiting to be simulated, e.g. after the game was paused
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    // Adds a frame's worth of time to the accumulator
+    pub fn advance(&mut self, frame_time: f64) {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+    }
+
+    // True while there is at least one whole tick waiting to be simulated
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator >= self.dt {
+            self.accumulator -= self.dt;
+            return true;
+        }
+        false
+    }
+
+    // How far between the last tick and the next one we are, from 0.0 to 1.0
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.dt
+    }
+}
+
+// Longest a headless game may run before it is called off (10 minutes of game time)
+const MAX_HEADLESS_SECONDS: f64 = 60.0 * 10.0;
+
+// Runs the requested number of games without a window or audio.
+// The bot just spins and fires, games that drag on are cut off at MAX_HEADLESS_SECONDS.
+pub fn run_headless(games: u32, settings: &SimulationSettings) {
+    let dt = FixedTimestep::new(settings.tick_rate).dt();
+    let max_ticks = (MAX_HEADLESS_SECONDS / dt) as u64;
+    let mut sim = Simulation::new(settings);
+    let mut key_manager: HashMap<String,bool> = HashMap::new();
+    let mut ticks = 0u64;
+
+    while sim.games_played() < games {
+        crate::utils::key_down(&mut key_manager, "D".to_string());
+        crate::utils::key_down(&mut key_manager, " ".to_string());
+        sim.step(&mut key_manager, dt);
+        ticks += 1;
+
+        if game::is_game_over(&sim.ecs) || ticks >= max_ticks {
+            let seed = sim.ecs.read_resource::<crate::Seed>().0;
+            println!("Game {} (seed {}) finished with score {} after {} ticks", sim.games_played() + 1, seed, sim.score(), ticks);
+            sim.games_played += 1;
+            sim.reset();
+            ticks = 0;
+        }
+    }
+}
+
+// Plays a recorded game back without a window and returns the score it ends on
+pub fn run_replay(replay: replay::Replay) -> u32 {
+    let dt = FixedTimestep::new(replay.settings.tick_rate).dt();
+    let mut sim = Simulation::new(&replay.settings);
+    let mut key_manager: HashMap<String,bool> = HashMap::new();
+    let mut playback = replay::Playback::new(replay);
+
+    while let Some(frame) = playback.next_frame() {
+        frame.apply_keys(&mut key_manager);
+        sim.apply_commands(&frame);
+        sim.step(&mut key_manager, dt);
+    }
+
+    sim.score()
+}
+
+// How many ticks the benchmark times
+const BENCHMARK_TICKS: u32 = 600;
+
+// Times the simulation with a field of asteroids and reports how many ticks it manages per second.
+// The player is made invincible so a collision can't reset the world halfway through.
+pub fn run_benchmark(asteroids: u32, settings: &SimulationSettings) {
+    let dt = FixedTimestep::new(settings.tick_rate).dt();
+    let mut sim = Simulation::new(settings);
+    let mut key_manager: HashMap<String,bool> = HashMap::new();
+    game::toggle_invincibility(&mut sim.ecs);
+    game::create_random_asteroids(&mut sim.ecs, asteroids);
+
+    let start = Instant::now();
+    for _ in 0..BENCHMARK_TICKS {
+        sim.step(&mut key_manager, dt);
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!("{} asteroids: {} ticks in {:.3}s, {:.1} ticks per second",
+        game::get_asteroid_count(&sim.ecs), BENCHMARK_TICKS, elapsed, BENCHMARK_TICKS as f64 / elapsed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(sim: &Simulation) -> Vec<(f64, f64)> {
+        let positions = sim.ecs.read_storage::<components::Position>();
+        positions.join().map(|pos| (pos.x, pos.y)).collect()
+    }
+
+    fn play(sim: &mut Simulation, ticks: u32) {
+        let mut key_manager: HashMap<String,bool> = HashMap::new();
+        crate::utils::key_down(&mut key_manager, "D".to_string());
+        crate::utils::key_down(&mut key_manager, " ".to_string());
+        for _ in 0..ticks {
+            sim.step(&mut key_manager, 1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn a_reset_game_is_reproduced_by_its_seed() {
+        let settings = SimulationSettings { seed: 7, tick_rate: 60, asteroid_collisions: false, playfield: physics::Playfield::default() };
+        let mut sim = Simulation::new(&settings);
+        play(&mut sim, 120);
+        sim.reset();
+        let seed = sim.ecs.read_resource::<crate::Seed>().0;
+        assert_ne!(seed, settings.seed);
+
+        //Asteroids are only placed at random once a level is cleared, so spawn some straight away
+        let mut fresh = Simulation::new(&SimulationSettings { seed, ..settings });
+        game::create_random_asteroids(&mut sim.ecs, 20);
+        game::create_random_asteroids(&mut fresh.ecs, 20);
+        play(&mut sim, 120);
+        play(&mut fresh, 120);
+        assert_eq!(positions(&sim), positions(&fresh));
+        assert_eq!(sim.score(), fresh.score());
+    }
+}
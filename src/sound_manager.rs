@@ -3,59 +3,120 @@ use kira::{
         AudioManager, AudioManagerSettings,
         backend::cpal::CpalBackend,
     },
-    sound::static_sound::{StaticSoundData, StaticSoundSettings, StaticSoundHandle, PlaybackState},
+    sound::static_sound::{StaticSoundData, StaticSoundHandle, PlaybackState},
     tween::Tween,
     LoopBehavior,
 };
 use std::collections::HashMap;
+use std::rc::Rc;
 
+use crate::assets::AssetError;
+use crate::texture_manager::{SoundCache, SoundLoader};
+use crate::events::GameEvent;
+
+// Whatever plays the game's sounds, picked once at startup
+pub trait AudioBackend {
+    // Sounds are played by their manifest id. One that fails to load stays silent, the error is for the startup report.
+    // Sounds started with resume_sound loop, play_sound plays them once.
+    fn load_sound(&mut self, id: &str, path: &str) -> Result<(), AssetError>;
+    fn play_sound(&mut self, id: &str);
+    fn resume_sound(&mut self, id: &str);
+    fn stop_sound(&mut self, id: &str);
+
+    // Picks up a changed file, the old sound keeps playing if the new one can't be read
+    fn reload_sound(&mut self, id: &str, path: &str) -> Result<(), AssetError> {
+        self.load_sound(id, path)
+    }
+
+    // Drops decoded sounds no id holds any more, e.g. the old copy of one that was reloaded
+    fn evict_unused(&mut self) -> usize {
+        0
+    }
+
+    // Plays whatever goes with a game event, every sound used here was loaded at startup
+    fn play_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::MissileFired => self.play_sound(crate::SHOOT_SOUND),
+            GameEvent::MissileExpired
+            | GameEvent::AsteroidDestroyed { .. } => self.play_sound(crate::RELOAD_SOUND),
+            GameEvent::ThrusterStarted => self.resume_sound(crate::THRUSTER_SOUND),
+            GameEvent::ThrusterStopped => self.stop_sound(crate::THRUSTER_SOUND),
+            GameEvent::PlayerDied { .. }
+            | GameEvent::LevelCleared { .. } => {}
+        }
+    }
+}
+
+// Picks the backend named by `--audio`, falling back to silence if there is no audio device
+pub fn create_backend(name: Option<&str>) -> Box<dyn AudioBackend> {
+    match name {
+        Some("null") => Box::new(NullAudio::new()),
+        Some("cpal") | None => match SoundManager::new() {
+            Ok(manager) => Box::new(manager),
+            Err(e) => {
+                println!("Failed to load Kira Audio Engine ({}), continuing without sound", e);
+                Box::new(NullAudio::new())
+            }
+        },
+        Some(other) => {
+            println!("Unknown audio backend {}, continuing without sound", other);
+            Box::new(NullAudio::new())
+        }
+    }
+}
+
+static SOUND_LOADER: SoundLoader = SoundLoader;
+
+// Plays sounds through kira on the default cpal output device
 pub struct SoundManager {
     pub sound_manager: AudioManager::<CpalBackend>,
-    pub sounds: HashMap::<String, StaticSoundData>,
+    pub sounds: SoundCache<'static>,
+    pub loaded: HashMap::<String, Rc<StaticSoundData>>, //Sound id to its data, holding it keeps it out of evict_unused
     pub loop_sounds: HashMap::<String, StaticSoundHandle>
 }
 
 impl SoundManager {
-    pub fn new() -> Self {
-        Self {
-            sound_manager: AudioManager::<CpalBackend>::new(AudioManagerSettings::default()).expect("Failed to load Kira Audio Engine"),
-            sounds: HashMap::new(),
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            sound_manager: AudioManager::<CpalBackend>::new(AudioManagerSettings::default()).map_err(|e| e.to_string())?,
+            sounds: SoundCache::new(&SOUND_LOADER),
+            loaded: HashMap::new(),
             loop_sounds: HashMap::new(),
-        }
+        })
     }
 
-    pub fn play_sound(&mut self, filename: String) {
-        if self.sounds.contains_key(&filename){
-            if let Some(x) = self.sounds.get_mut(&filename) {
-                self.sound_manager.play(x.clone()).expect("Failed to play sounds");
-            }
-        }else{
-            println!("Sound doesn't exist");
-        }
+    fn sound(&self, id: &str) -> Option<Rc<StaticSoundData>> {
+        self.loaded.get(id).cloned()
     }
 
-    pub fn start_sound(&mut self, filename: &String) {
-        match self.sounds.get_mut(filename) {
-            Some(x) => {
-                match self.sound_manager.play(x.clone()) {
-                    Ok(res) => {
-                        self.loop_sounds.entry( filename.to_string() )
-                            .or_insert(res);
-                    },
-                    Err(e) => {
-                        println!("{}", e);
-                    }
+    fn start_sound(&mut self, id: &str) {
+        //A sound that never loaded just stays silent
+        if let Some(x) = self.sound(id) {
+            let looped = x.with_modified_settings(|settings| settings.loop_behavior(LoopBehavior{start_position:0.0}));
+            match self.sound_manager.play(looped) {
+                Ok(res) => {
+                    self.loop_sounds.entry( id.to_string() )
+                        .or_insert(res);
+                },
+                Err(e) => {
+                    println!("{}", e);
                 }
-            },
-            None => {
-                println!("Error when playing music");
             }
         }
     }
+}
 
-    pub fn stop_sound(&mut self, filename: &String) {
-        if self.loop_sounds.contains_key(&filename.to_string()){
-            if let Some(x) = self.loop_sounds.get_mut(&filename.to_string()) {
+impl AudioBackend for SoundManager {
+    fn play_sound(&mut self, id: &str) {
+        if let Some(x) = self.sound(id) {
+            self.sound_manager.play((*x).clone()).expect("Failed to play sounds");
+        }
+        //Sounds that failed to load were reported at startup and just stay silent
+    }
+
+    fn stop_sound(&mut self, id: &str) {
+        if self.loop_sounds.contains_key(id){
+            if let Some(x) = self.loop_sounds.get_mut(id) {
                 if x.state() == PlaybackState::Playing {
                     // Pausing sound
                     x.pause(Tween::default()).expect("Failed to pause sound");
@@ -64,9 +125,9 @@ impl SoundManager {
         }
     }
 
-    pub fn resume_sound(&mut self,filename: &String) {
-        if self.loop_sounds.contains_key(&filename.to_string()) {
-            if let Some(x) = self.loop_sounds.get_mut(&filename.to_string()) {
+    fn resume_sound(&mut self, id: &str) {
+        if self.loop_sounds.contains_key(id) {
+            if let Some(x) = self.loop_sounds.get_mut(id) {
                 if x.state() == PlaybackState::Paused  {
                     // Resuming sound
                     x.resume(Tween::default()).expect("Failed to resume sound");
@@ -77,21 +138,66 @@ impl SoundManager {
             }
         }else{
             // Starting the sound for the first time
-            self.start_sound(filename);
+            self.start_sound(id);
         }
     }
 
-    pub fn load_sound(&mut self, filename: &String, looped: bool) {
-        let mut sss = StaticSoundSettings::default();
-        if looped {
-            sss.loop_behavior = Some(LoopBehavior{start_position:0.0});
+    fn load_sound(&mut self, id: &str, path: &str) -> Result<(), AssetError> {
+        let sound = self.sounds.load(path)?;
+        self.loaded.insert(id.to_string(), sound);
+        Ok(())
+    }
+
+    // Looping sounds are restarted with the new data if they were playing
+    fn reload_sound(&mut self, id: &str, path: &str) -> Result<(), AssetError> {
+        let sound = self.sounds.reload(path)?;
+        self.loaded.insert(id.to_string(), sound);
+        if let Some(mut handle) = self.loop_sounds.remove(id) {
+            let was_playing = handle.state() == PlaybackState::Playing;
+            handle.stop(Tween::default()).ok();
+            if was_playing {
+                self.start_sound(id);
+            }
         }
+        Ok(())
+    }
+
+    fn evict_unused(&mut self) -> usize {
+        self.sounds.evict_unused()
+    }
+}
+
+// Plays nothing and just logs what would have been heard, for machines without an audio device
+#[derive(Default)]
+pub struct NullAudio {
+    pub calls: Vec<(&'static str, String)> //Every play, loop and stop in order, e.g. ("play", "shoot")
+}
 
-        self.sounds.entry( (&filename).to_string() ).or_insert(
-            StaticSoundData::from_file(
-                filename,
-                sss
-            ).expect("Failed to load sound")
-        );
+impl NullAudio {
+    pub fn new() -> Self {
+        Self::default()
     }
-}
\ No newline at end of file
+
+    fn log(&mut self, action: &'static str, id: &str) {
+        println!("[audio] {} {}", action, id);
+        self.calls.push((action, id.to_string()));
+    }
+}
+
+impl AudioBackend for NullAudio {
+    fn load_sound(&mut self, _id: &str, _path: &str) -> Result<(), AssetError> {
+        Ok(())
+    }
+
+    fn play_sound(&mut self, id: &str) {
+        self.log("play", id);
+    }
+
+    fn resume_sound(&mut self, id: &str) {
+        self.log("loop", id);
+    }
+
+    fn stop_sound(&mut self, id: &str) {
+        self.log("stop", id);
+    }
+}
@@ -0,0 +1,221 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::render::{BlendMode, WindowCanvas};
+use sdl2::ttf::Font;
+
+use crate::display::DisplayMode;
+use crate::hud::HudLayout;
+use crate::text::TextCache;
+
+const MAX_INITIALS: usize = 3;
+
+// How dark the world gets behind the pause menu, 0 leaves it alone and 255 blacks it out
+const PAUSE_DIM: u8 = 140;
+
+// HUD slots for menu lines, see hud.toml
+const MENU_SLOTS: [&str; 3] = ["menu_0", "menu_1", "menu_2"];
+
+// The screens the game moves between. Only Playing runs the simulation, the others draw over the world as it was left
+pub enum AppState {
+    Title,
+    Playing,
+    Paused,
+    GameOver { score: u32 },
+    HighScoreEntry { score: u32, initials: String },
+    Settings { selected: usize, back: Box<AppState> } //Goes back to wherever it was opened from
+}
+
+// What the main loop should do after a state has seen an event
+pub enum Transition {
+    Stay,
+    To(AppState),
+    Toggle(Setting),
+    Quit
+}
+
+// Switches kept for the whole session, changed from the settings screen or their hotkeys
+pub struct Options {
+    pub music: bool,
+    pub unlocked_fps: bool,
+    pub display_mode: DisplayMode
+}
+
+#[derive(Clone, Copy)]
+pub enum Setting {
+    Music,
+    UnlockedFps,
+    Display
+}
+
+const SETTINGS: [Setting; 3] = [Setting::Music, Setting::UnlockedFps, Setting::Display];
+
+impl Setting {
+    fn label(self, options: &Options) -> String {
+        match self {
+            Setting::Music => format!("Music: {}", on_off(options.music)),
+            Setting::UnlockedFps => format!("Unlock FPS: {}", on_off(options.unlocked_fps)),
+            Setting::Display => format!("Display: {}", match options.display_mode {
+                DisplayMode::Windowed => "Windowed",
+                DisplayMode::Borderless => "Borderless",
+                DisplayMode::Fullscreen => "Fullscreen"
+            })
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "On" } else { "Off" }
+}
+
+impl AppState {
+    pub fn is_playing(&self) -> bool {
+        matches!(self, AppState::Playing)
+    }
+
+    // Where a finished game goes, a new best score gets initials first.
+    // The old holder's initials are cleared straight away so a skipped entry doesn't leave them on the new score
+    pub fn game_over(score: u32, previous_best: u32) -> Self {
+        if score > previous_best {
+            crate::GAMESTATE.lock().unwrap().highscore_name.clear();
+            AppState::HighScoreEntry { score, initials: String::new() }
+        } else {
+            AppState::GameOver { score }
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Transition {
+        //Typed initials arrive as text so the hotkeys below don't apply while they are entered
+        if let AppState::HighScoreEntry { score, initials } = self {
+            return match event {
+                Event::TextInput { text, .. } => {
+                    for c in text.chars().filter(|c| c.is_ascii_alphanumeric()) {
+                        if initials.len() < MAX_INITIALS {
+                            initials.push(c.to_ascii_uppercase());
+                        }
+                    }
+                    Transition::Stay
+                },
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                    initials.pop();
+                    Transition::Stay
+                },
+                Event::KeyUp { keycode: Some(Keycode::Return), .. } if !initials.is_empty() => {
+                    crate::GAMESTATE.lock().unwrap().highscore_name = initials.to_string();
+                    Transition::To(AppState::GameOver { score: *score })
+                },
+                Event::KeyUp { keycode: Some(Keycode::Escape), .. } => Transition::To(AppState::GameOver { score: *score }),
+                _ => Transition::Stay
+            };
+        }
+
+        let key = match event {
+            Event::KeyUp { keycode: Some(key), .. } => *key,
+            _ => return Transition::Stay
+        };
+        match key {
+            Keycode::M => return Transition::Toggle(Setting::Music),
+            Keycode::U => return Transition::Toggle(Setting::UnlockedFps),
+            Keycode::F11 => return Transition::Toggle(Setting::Display),
+            _ => {}
+        }
+
+        match self {
+            AppState::Title => match key {
+                Keycode::Return => Transition::To(AppState::Playing),
+                Keycode::S => Transition::To(AppState::Settings { selected: 0, back: Box::new(AppState::Title) }),
+                Keycode::Escape => Transition::Quit,
+                _ => Transition::Stay
+            },
+            AppState::Playing => match key {
+                Keycode::Escape | Keycode::P => Transition::To(AppState::Paused),
+                _ => Transition::Stay
+            },
+            AppState::Paused => match key {
+                Keycode::Escape | Keycode::P | Keycode::Return => Transition::To(AppState::Playing),
+                Keycode::S => Transition::To(AppState::Settings { selected: 0, back: Box::new(AppState::Paused) }),
+                Keycode::Q => Transition::To(AppState::Title),
+                _ => Transition::Stay
+            },
+            AppState::GameOver { .. } => match key {
+                Keycode::Return | Keycode::Escape => Transition::To(AppState::Title),
+                _ => Transition::Stay
+            },
+            AppState::Settings { selected, back } => match key {
+                Keycode::Up => {
+                    *selected = (*selected + SETTINGS.len() - 1) % SETTINGS.len();
+                    Transition::Stay
+                },
+                Keycode::Down => {
+                    *selected = (*selected + 1) % SETTINGS.len();
+                    Transition::Stay
+                },
+                Keycode::Return | Keycode::Left | Keycode::Right => Transition::Toggle(SETTINGS[*selected]),
+                Keycode::Escape => Transition::To(std::mem::replace(&mut **back, AppState::Title)),
+                _ => Transition::Stay
+            },
+            AppState::HighScoreEntry { .. } => Transition::Stay
+        }
+    }
+
+    // Draws the state's own text on top of the world and HUD
+    pub fn render(&self, canvas: &mut WindowCanvas, font: &Font, text_cache: &mut TextCache, hud: &HudLayout, options: &Options) -> Result<(), String> {
+        let (title, subtitle, menu, hint): (&str, Option<String>, Vec<String>, &str) = match self {
+            AppState::Playing => return Ok(()),
+            AppState::Title => (
+                "ASTEROIDS",
+                None,
+                vec!["Enter  Play".to_string(), "S  Settings".to_string(), "Esc  Quit".to_string()],
+                ""
+            ),
+            AppState::Paused => (
+                "Paused",
+                None,
+                vec!["P  Resume".to_string(), "S  Settings".to_string(), "Q  Quit to title".to_string()],
+                ""
+            ),
+            AppState::GameOver { score } => (
+                "Game Over",
+                Some(format!("Score: {}", score)),
+                Vec::new(),
+                "Enter to continue"
+            ),
+            AppState::HighScoreEntry { score, initials } => (
+                "New High Score!",
+                Some(format!("Score: {}", score)),
+                vec![String::new(), format!("Initials: {}_", initials)],
+                "Type your initials and press Enter, Esc to skip"
+            ),
+            AppState::Settings { selected, .. } => (
+                "Settings",
+                None,
+                SETTINGS.iter().enumerate().map(|(index, setting)| {
+                    let marker = if index == *selected { "> " } else { "" };
+                    format!("{}{}", marker, setting.label(options))
+                }).collect(),
+                "Up/Down choose  Enter change  Esc back"
+            )
+        };
+
+        if let AppState::Paused = self {
+            canvas.set_blend_mode(BlendMode::Blend);
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, PAUSE_DIM));
+            canvas.fill_rect(None)?;
+            canvas.set_blend_mode(BlendMode::None);
+        }
+
+        hud.draw(canvas, font, text_cache, "title", title)?;
+        if let Some(subtitle) = subtitle {
+            hud.draw(canvas, font, text_cache, "subtitle", &subtitle)?;
+        }
+        for (slot, line) in MENU_SLOTS.iter().zip(menu.iter()) {
+            if !line.is_empty() {
+                hud.draw(canvas, font, text_cache, slot, line)?;
+            }
+        }
+        if !hint.is_empty() {
+            hud.draw(canvas, font, text_cache, "hint", hint)?;
+        }
+        Ok(())
+    }
+}
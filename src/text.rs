@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::ttf::Font;
+use sdl2::video::WindowContext;
+
+// Keeps the rasterized texture for each piece of HUD text and only redraws it when the string changes.
+// Entries are keyed by slot, e.g. "score", so a changing value replaces its old texture instead of piling up
+pub struct TextCache<'l> {
+    texture_creator: &'l TextureCreator<WindowContext>,
+    entries: HashMap<&'static str, (String, Texture<'l>)>
+}
+
+impl<'l> TextCache<'l> {
+    pub fn new(texture_creator: &'l TextureCreator<WindowContext>) -> Self {
+        Self {
+            texture_creator,
+            entries: HashMap::new()
+        }
+    }
+
+    // Drops every texture, e.g. when the font changes
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn draw(&mut self, canvas: &mut WindowCanvas, font: &Font, slot: &'static str, text: &str, target: Rect) -> Result<(), String> {
+        let stale = match self.entries.get(slot) {
+            Some((cached, _)) => cached != text,
+            None => true
+        };
+        if stale {
+            let surface = font
+                .render(text)
+                .blended(Color::RGBA(0,0,0,255))
+                .map_err(|e| e.to_string())?;
+            let texture = self.texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| e.to_string())?;
+            self.entries.insert(slot, (text.to_string(), texture));
+        }
+
+        let (_, texture) = &self.entries[slot];
+        canvas.copy(texture, None, Some(target))
+    }
+}
@@ -1,11 +1,20 @@
 use std::collections::HashMap;
 use sdl2::render::{Texture, TextureCreator};
 use sdl2::image::{LoadTexture};
+use sdl2::ttf::{Font, FontStyle, Sdl2TtfContext};
+use kira::sound::{
+    static_sound::{StaticSoundData, StaticSoundSettings},
+    FromFileError,
+};
 use std::borrow::Borrow;
 use std::hash::Hash;
 use std::rc::Rc;
 
+use crate::assets::AssetError;
+
 pub type TextureManager<'l, T> = ResourceManager<'l, String, Texture<'l>, TextureCreator<T>>;
+pub type FontManager<'l> = ResourceManager<'l, FontDetails, Font<'l, 'static>, Sdl2TtfContext>;
+pub type SoundCache<'l> = ResourceManager<'l, String, StaticSoundData, SoundLoader>;
 
 pub struct ResourceManager<'l, K, R, L>
     where
@@ -14,6 +23,7 @@ pub struct ResourceManager<'l, K, R, L>
 {
     loader: &'l L,
     cache: HashMap<K, Rc<R>>,
+    fallback: Option<Rc<R>>,
 }
 
 impl<'l, K, R, L> ResourceManager<'l, K, R, L>
@@ -25,12 +35,49 @@ impl<'l, K, R, L> ResourceManager<'l, K, R, L>
         ResourceManager {
             cache: HashMap::new(),
             loader: loader,
+            fallback: None,
         }
     }
 
+    // Stands in for anything that fails to load from now on
+    pub fn set_fallback(&mut self, resource: R) {
+        self.fallback = Some(Rc::new(resource));
+    }
+
+    // Looks a resource up without ever loading it, for places that must not touch the disk
+    pub fn get<D>(&self, details: &D) -> Option<Rc<R>>
+        where
+            D: Eq + Hash + ?Sized,
+            K: Borrow<D>,
+    {
+        self.cache.get(details).cloned()
+    }
+
+    // Drops everything only the cache still holds, hold on to the Rc from `load` to keep an asset around
+    pub fn evict_unused(&mut self) -> usize {
+        let before = self.cache.len();
+        self.cache.retain(|_, resource| Rc::strong_count(resource) > 1);
+        before - self.cache.len()
+    }
+
+    // Loads a resource again and swaps it into the cache, used when the file changes on disk.
+    // If the new file is broken the old copy stays so a half-saved file can't break the game
+    pub fn reload<D>(&mut self, details: &D) -> Result<Rc<R>, AssetError>
+        where
+            L: ResourceLoader<'l, R, Args = D>,
+            D: Eq + Hash + ?Sized,
+            K: Borrow<D> + for<'a> From<&'a D>,
+    {
+        let resource = Rc::new(self.loader.load(details)?);
+        self.cache.insert(details.into(), resource.clone());
+        Ok(resource)
+    }
+
     // Generics magic to allow a HashMap to use String as a key
     // while allowing it to use &str for gets
-    pub fn load<D>(&mut self, details: &D) -> Result<Rc<R>, String>
+    // A failed load still reports its error, but the fallback is cached under its key
+    // so later loads get the placeholder instead of hitting the disk again
+    pub fn load<D>(&mut self, details: &D) -> Result<Rc<R>, AssetError>
         where
             L: ResourceLoader<'l, R, Args = D>,
             D: Eq + Hash + ?Sized,
@@ -38,9 +85,19 @@ impl<'l, K, R, L> ResourceManager<'l, K, R, L>
     {
         self.cache.get(details).cloned().map_or_else(
             || {
-                let resource = Rc::new(self.loader.load(details)?);
-                self.cache.insert(details.into(), resource.clone());
-                Ok(resource)
+                match self.loader.load(details) {
+                    Ok(resource) => {
+                        let resource = Rc::new(resource);
+                        self.cache.insert(details.into(), resource.clone());
+                        Ok(resource)
+                    },
+                    Err(e) => {
+                        if let Some(fallback) = &self.fallback {
+                            self.cache.insert(details.into(), fallback.clone());
+                        }
+                        Err(e)
+                    }
+                }
             },
             Ok,
         )
@@ -50,14 +107,55 @@ impl<'l, K, R, L> ResourceManager<'l, K, R, L>
 // Generic trait to Load any Resource Kind
 pub trait ResourceLoader<'l, R> {
     type Args: ?Sized;
-    fn load(&'l self, data: &Self::Args) -> Result<R, String>;
+    fn load(&'l self, data: &Self::Args) -> Result<R, AssetError>;
 }
 
 // TextureCreator knows how to load Textures
 impl<'l, T> ResourceLoader<'l, Texture<'l>> for TextureCreator<T> {
     type Args = str;
-    fn load(&'l self, path: &str) -> Result<Texture, String> {
+    fn load(&'l self, path: &str) -> Result<Texture, AssetError> {
         // println!("LOADED A TEXTURE");
-        self.load_texture(path)
+        self.load_texture(path).map_err(|e| AssetError::from_load(path, e))
+    }
+}
+
+// Fonts are cached per file, point size and style
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct FontDetails {
+    pub path: String,
+    pub size: u16,
+    pub style: FontStyle,
+}
+
+impl<'a> From<&'a FontDetails> for FontDetails {
+    fn from(details: &'a FontDetails) -> FontDetails {
+        details.clone()
+    }
+}
+
+// The ttf context knows how to load Fonts
+impl<'l> ResourceLoader<'l, Font<'l, 'static>> for Sdl2TtfContext {
+    type Args = FontDetails;
+    fn load(&'l self, details: &FontDetails) -> Result<Font<'l, 'static>, AssetError> {
+        let mut font = self.load_font(&details.path, details.size)
+            .map_err(|e| AssetError::from_load(&details.path, e))?;
+        font.set_style(details.style);
+        Ok(font)
+    }
+}
+
+// Decodes whole sound files up front so playing them never reads from disk
+pub struct SoundLoader;
+
+impl<'l> ResourceLoader<'l, StaticSoundData> for SoundLoader {
+    type Args = str;
+    fn load(&'l self, path: &str) -> Result<StaticSoundData, AssetError> {
+        StaticSoundData::from_file(path, StaticSoundSettings::default()).map_err(|e| match e {
+            FromFileError::UnsupportedChannelConfiguration => AssetError::Unsupported{
+                path: path.to_string(),
+                reason: e.to_string()
+            },
+            _ => AssetError::from_load(path, e)
+        })
     }
-}
\ No newline at end of file
+}